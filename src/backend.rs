@@ -0,0 +1,208 @@
+use cgmath::{Matrix4, Vector2};
+
+use image::RgbImage;
+
+use crate::color::Conversion;
+use crate::error::{Error, Result};
+
+/// Execution engine for the primitive image operations.
+///
+/// Textures hold linear float rgba and follow OpenGL conventions:
+/// texture coordinates span [0, 1] with the origin at the bottom left,
+/// lookups are bilinearly filtered and wrap with mirrored repeat.
+/// Operations render into a new texture sized by `output_size` of their inputs.
+pub trait Backend {
+    type Texture;
+
+    fn dimensions(&self, texture: &Self::Texture) -> (u32, u32);
+
+    /// Multiply each color with `transform`. Alpha is the fourth component.
+    fn transform(&self, texture: &Self::Texture, transform: Matrix4<f32>) -> Self::Texture;
+
+    /// Apply a non-linear per pixel color conversion. Alpha is kept.
+    fn convert(&self, texture: &Self::Texture, conversion: Conversion) -> Self::Texture;
+
+    /// Resample to `size`. Halving a dimension averages pairs of pixels.
+    fn resize(&self, texture: &Self::Texture, size: (u32, u32)) -> Self::Texture;
+
+    /// Offset texture coordinates by `shift`
+    fn shift(&self, texture: &Self::Texture, shift: Vector2<f32>) -> Self::Texture;
+
+    fn diff(&self, tex1: &Self::Texture, tex2: &Self::Texture, use_abs: bool) -> Self::Texture;
+
+    fn add(&self, tex1: &Self::Texture, tex2: &Self::Texture) -> Self::Texture;
+
+    fn mul(&self, tex1: &Self::Texture, tex2: &Self::Texture) -> Self::Texture;
+
+    /// Take red from `r`, green from `g` and blue from `b`
+    fn channels(&self, r: &Self::Texture, g: &Self::Texture, b: &Self::Texture) -> Self::Texture;
+
+    /// Upload tightly packed linear rgb data with rows from bottom to top.
+    /// Fails with `Error::SizeMismatch` if `data` does not hold `width * height` pixels.
+    fn upload(&self, width: u32, height: u32, data: Vec<f32>) -> Result<Self::Texture>;
+
+    /// Upload linear rgba with rows from bottom to top. Fails like `upload`.
+    fn upload_rgba(&self, width: u32, height: u32, data: &[[f32; 4]]) -> Result<Self::Texture>;
+
+    /// Upload an sRGB encoded image and decode it to linear
+    fn upload_srgb(&self, image: &RgbImage) -> Result<Self::Texture>;
+
+    /// Read back linear rgba with rows from bottom to top
+    fn read(&self, texture: &Self::Texture) -> Vec<[f32; 4]>;
+}
+
+/// Size of the output of an operation with inputs of size `sizes`.
+/// Smaller inputs are stretched over the output so that for example
+/// 1x1 textures act as constants.
+pub fn output_size(sizes: &[(u32, u32)]) -> (u32, u32) {
+    sizes
+        .iter()
+        .fold((1, 1), |(w, h), &(tw, th)| (w.max(tw), h.max(th)))
+}
+
+/// Check that `len` values of `channels` components fill a `width` by `height` texture
+pub fn check_size(len: usize, channels: usize, width: u32, height: u32) -> Result<()> {
+    let expected = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(channels));
+    if expected == Some(len) {
+        Ok(())
+    } else {
+        Err(Error::SizeMismatch(len / channels, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Matrix4, Vector2, Vector4};
+
+    use image::RgbImage;
+
+    use super::{output_size, Backend};
+    use crate::color::{srgb_decode, Conversion, TransferFunction};
+    use crate::error::Error;
+    use crate::process::CpuProcessor;
+
+    type CpuTexture = <CpuProcessor as Backend>::Texture;
+
+    /// Texture with gray values `values`, rows from bottom to top
+    fn gray(width: u32, height: u32, values: &[f32]) -> CpuTexture {
+        let data = values.iter().flat_map(|&v| vec![v, v, v]).collect();
+        CpuProcessor.upload(width, height, data).unwrap()
+    }
+
+    fn assert_pixels(texture: &CpuTexture, expected: &[[f32; 4]]) {
+        let pixels = CpuProcessor.read(texture);
+        assert_eq!(pixels.len(), expected.len());
+        for (pixel, expected) in pixels.iter().zip(expected) {
+            for (c, e) in pixel.iter().zip(expected) {
+                assert!((c - e).abs() < 1e-5, "{:?} != {:?}", pixels, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn output_size_is_largest() {
+        assert_eq!(output_size(&[(4, 1), (1, 1), (2, 3)]), (4, 3));
+    }
+
+    #[test]
+    fn transform() {
+        let texture = gray(2, 1, &[0.25, 0.5]);
+        let mut mat = Matrix4::from_nonuniform_scale(2.0, 1.0, 0.0);
+        mat.w = Vector4::new(0.1, 0.0, 1.0, 1.0);
+        let result = CpuProcessor.transform(&texture, mat);
+        assert_pixels(&result, &[[0.6, 0.25, 1.0, 1.0], [1.1, 0.5, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn shift_mirrors_at_edges() {
+        let texture = gray(4, 1, &[0.0, 1.0, 2.0, 3.0]);
+        let left = CpuProcessor.shift(&texture, Vector2::new(0.25, 0.0));
+        assert_pixels(&left, &[[1.0, 1.0, 1.0, 1.0], [2.0, 2.0, 2.0, 1.0], [3.0, 3.0, 3.0, 1.0], [3.0, 3.0, 3.0, 1.0]]);
+        let half = CpuProcessor.shift(&texture, Vector2::new(-0.125, 0.0));
+        assert_pixels(&half, &[[0.0, 0.0, 0.0, 1.0], [0.5, 0.5, 0.5, 1.0], [1.5, 1.5, 1.5, 1.0], [2.5, 2.5, 2.5, 1.0]]);
+    }
+
+    #[test]
+    fn resize_halving_averages_pairs() {
+        let texture = gray(4, 1, &[0.0, 1.0, 2.0, 4.0]);
+        let result = CpuProcessor.resize(&texture, (2, 1));
+        assert_pixels(&result, &[[0.5, 0.5, 0.5, 1.0], [3.0, 3.0, 3.0, 1.0]]);
+    }
+
+    #[test]
+    fn diff_add_mul() {
+        let a = gray(2, 1, &[0.25, 1.0]);
+        let b = gray(2, 1, &[0.5, 0.5]);
+        assert_pixels(
+            &CpuProcessor.diff(&a, &b, false),
+            &[[-0.25, -0.25, -0.25, 0.0], [0.5, 0.5, 0.5, 0.0]],
+        );
+        assert_pixels(
+            &CpuProcessor.diff(&a, &b, true),
+            &[[0.25, 0.25, 0.25, 0.0], [0.5, 0.5, 0.5, 0.0]],
+        );
+        assert_pixels(&CpuProcessor.add(&a, &b), &[[0.75, 0.75, 0.75, 2.0], [1.5, 1.5, 1.5, 2.0]]);
+        assert_pixels(&CpuProcessor.mul(&a, &b), &[[0.125, 0.125, 0.125, 1.0], [0.5, 0.5, 0.5, 1.0]]);
+    }
+
+    #[test]
+    fn single_pixel_inputs_are_constants() {
+        let a = gray(3, 1, &[1.0, 2.0, 3.0]);
+        let b = gray(1, 1, &[10.0]);
+        assert_pixels(
+            &CpuProcessor.add(&a, &b),
+            &[[11.0, 11.0, 11.0, 2.0], [12.0, 12.0, 12.0, 2.0], [13.0, 13.0, 13.0, 2.0]],
+        );
+    }
+
+    #[test]
+    fn channels() {
+        let r = gray(1, 1, &[0.1]);
+        let g = gray(1, 1, &[0.2]);
+        let b = gray(1, 1, &[0.3]);
+        assert_pixels(&CpuProcessor.channels(&r, &g, &b), &[[0.1, 0.2, 0.3, 1.0]]);
+    }
+
+    #[test]
+    fn upload_and_read_rows_bottom_to_top() {
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let texture = CpuProcessor.upload(1, 2, data).unwrap();
+        assert_pixels(&texture, &[[1.0, 2.0, 3.0, 1.0], [4.0, 5.0, 6.0, 1.0]]);
+        let rgba = [[0.1, -0.2, 3.0, 0.5], [1.0, 2.0, 3.0, 4.0]];
+        let texture = CpuProcessor.upload_rgba(2, 1, &rgba).unwrap();
+        assert_pixels(&texture, &rgba);
+    }
+
+    #[test]
+    fn upload_size_mismatch() {
+        match CpuProcessor.upload(2, 2, vec![0.0; 9]) {
+            Err(Error::SizeMismatch(3, 2, 2)) => (),
+            _ => panic!("rgb data of the wrong size was uploaded"),
+        }
+        match CpuProcessor.upload(1, 1, vec![0.0; 4]) {
+            Err(Error::SizeMismatch(..)) => (),
+            _ => panic!("rgb data with a partial pixel was uploaded"),
+        }
+        match CpuProcessor.upload_rgba(3, 1, &[[0.0; 4]; 2]) {
+            Err(Error::SizeMismatch(2, 3, 1)) => (),
+            _ => panic!("rgba data of the wrong size was uploaded"),
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        assert!((srgb_decode(0.5) - 0.214_041).abs() < 1e-5);
+        assert!((srgb_decode(0.04) - 0.04 / 12.92).abs() < 1e-7);
+        let image = RgbImage::from_fn(256, 2, |x, y| image::Rgb([x as u8, 255 - x as u8, (x * y) as u8]));
+        let linear = CpuProcessor.upload_srgb(&image).unwrap();
+        let encoded = CpuProcessor.convert(&linear, Conversion::Encode(TransferFunction::Srgb));
+        let pixels = CpuProcessor.read(&encoded);
+        // The image has its first row at the top and the texture at the bottom
+        for (pixel, expected) in pixels.chunks(256).rev().flatten().zip(image.pixels()) {
+            for c in 0..3 {
+                assert_eq!((pixel[c] * 255.0).round() as u8, expected[c]);
+            }
+            assert_eq!(pixel[3], 1.0);
+        }
+    }
+}
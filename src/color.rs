@@ -0,0 +1,705 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Matrix4, Vector3, Vector4};
+
+use serde::Deserialize;
+
+mod cmf;
+mod cvd;
+mod gamut;
+#[cfg(test)]
+mod glsl;
+mod icc;
+mod space;
+mod spectrum;
+mod tone;
+mod transfer;
+
+pub use self::cmf::Observer;
+pub use self::gamut::{ChromaSpace, GamutMapping};
+pub use self::icc::IccProfile;
+pub use self::space::{xy_to_xyz, RgbColorSpace, SRGB};
+pub use self::spectrum::{Light, Spectrum};
+pub use self::tone::ToneMapping;
+pub use self::transfer::{srgb_decode, TransferFunction};
+pub use self::cvd::{daltonize_matrix, machado_matrix, vienot_matrix, CvdModel, Deficiency};
+
+/// XYZ of the D65 white point of the sRGB matrices in `Image::rgb_to_xyz`
+pub const D65: Vector3<f32> = Vector3 {
+    x: 0.950_456,
+    y: 1.0,
+    z: 1.088_754,
+};
+
+/// CIE standard illuminants for the 2° observer
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Illuminant {
+    /// Incandescent light
+    A,
+    /// Horizon daylight, used by ICC profiles
+    D50,
+    /// Mid-morning daylight
+    D55,
+    /// Noon daylight, the white point of sRGB
+    D65,
+    /// Cool white fluorescent
+    F2,
+    /// Narrow band white fluorescent
+    F11,
+}
+
+impl Illuminant {
+    pub const ALL: [Illuminant; 6] = [
+        Illuminant::A,
+        Illuminant::D50,
+        Illuminant::D55,
+        Illuminant::D65,
+        Illuminant::F2,
+        Illuminant::F11,
+    ];
+
+    /// XYZ of the white point with Y = 1
+    pub fn white(self) -> Vector3<f32> {
+        match self {
+            Illuminant::A => Vector3::new(1.098_50, 1.0, 0.355_85),
+            Illuminant::D50 => Vector3::new(0.964_22, 1.0, 0.825_21),
+            Illuminant::D55 => Vector3::new(0.956_82, 1.0, 0.921_49),
+            Illuminant::D65 => D65,
+            Illuminant::F2 => Vector3::new(0.991_87, 1.0, 0.673_95),
+            Illuminant::F11 => Vector3::new(1.009_62, 1.0, 0.643_50),
+        }
+    }
+}
+
+/// Cone response model in which a chromatic adaptation scales the responses
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdaptationMethod {
+    #[default]
+    Bradford,
+    /// Cone space of CIECAM02
+    Cat02,
+    /// Hunt-Pointer-Estevez cone responses
+    VonKries,
+}
+
+impl AdaptationMethod {
+    /// CIE XYZ to the cone space of the method
+    pub fn cone_matrix(self) -> Matrix3<f32> {
+        match self {
+            AdaptationMethod::Bradford => Matrix3::new(
+                0.8951, 0.2664, -0.1614,
+                -0.7502, 1.7135, 0.0367,
+                0.0389, -0.0685, 1.0296,
+            ).transpose(),
+            AdaptationMethod::Cat02 => Matrix3::new(
+                0.7328, 0.4296, -0.1624,
+                -0.7036, 1.6975, 0.0061,
+                0.0030, 0.0136, 0.9834,
+            ).transpose(),
+            AdaptationMethod::VonKries => xyz_to_lms_matrix(),
+        }
+    }
+}
+
+/// Transform in CIE XYZ that maps colors seen under `from_white` to the corresponding
+/// colors under `to_white`
+pub fn adaptation_matrix(
+    from_white: Vector3<f32>,
+    to_white: Vector3<f32>,
+    method: AdaptationMethod,
+) -> Matrix3<f32> {
+    let to_cone = method.cone_matrix();
+    let from = to_cone * from_white;
+    let to = to_cone * to_white;
+    let scale = Matrix3::from_diagonal(Vector3::new(to.x / from.x, to.y / from.y, to.z / from.z));
+    to_cone.invert().unwrap() * scale * to_cone
+}
+
+/// Non-linear per pixel color conversions.
+///
+/// `apply` is the reference implementation used by the CPU backend.
+/// `shaders/convert.frag` implements the same functions for the GPU
+/// and selects them with `id`.
+#[derive(Clone, Copy, Debug)]
+pub enum Conversion {
+    /// CIE XYZ to CIE L*a*b* relative to `white`
+    XyzToLab { white: Vector3<f32> },
+    LabToXyz { white: Vector3<f32> },
+    /// L*a*b* to L*C*h with hue in degrees
+    LabToLch,
+    LchToLab,
+    /// Linear sRGB to OKLab
+    RgbToOklab,
+    OklabToRgb,
+    /// Rgb to hue in degrees, saturation and value
+    RgbToHsv,
+    /// Saturation is clamped to [0, 1] and hue wraps around
+    HsvToRgb,
+    /// Rgb to hue in degrees, saturation and lightness
+    RgbToHsl,
+    HslToRgb,
+    /// Rgb to hue in degrees, saturation and intensity
+    RgbToHsi,
+    HsiToRgb,
+    /// Transfer function from linear to encoded values
+    Encode(TransferFunction),
+    Decode(TransferFunction),
+    /// Brettel 1997 color vision deficiency simulation on linear sRGB
+    Brettel { deficiency: Deficiency, severity: f32 },
+    /// Bring rgb into the [0, 1] cube
+    GamutMap(GamutMapping),
+    /// Replace rgb outside the [0, 1] cube with `color`
+    GamutWarning { color: Vector3<f32> },
+    /// Compress linear rgb into [0, 1] with `white` as the white point
+    ToneMap { mapping: ToneMapping, white: f32 },
+}
+
+impl Conversion {
+    /// Identifier of the conversion in `convert.frag`
+    pub fn id(&self) -> i32 {
+        match self {
+            Conversion::XyzToLab { .. } => 0,
+            Conversion::LabToXyz { .. } => 1,
+            Conversion::LabToLch => 2,
+            Conversion::LchToLab => 3,
+            Conversion::RgbToOklab => 4,
+            Conversion::OklabToRgb => 5,
+            Conversion::RgbToHsv => 6,
+            Conversion::HsvToRgb => 7,
+            Conversion::RgbToHsl => 8,
+            Conversion::HslToRgb => 9,
+            Conversion::RgbToHsi => 10,
+            Conversion::HsiToRgb => 11,
+            Conversion::Encode(_) => 12,
+            Conversion::Decode(_) => 13,
+            Conversion::Brettel { .. } => 14,
+            Conversion::GamutMap(_) => 15,
+            Conversion::GamutWarning { .. } => 16,
+            Conversion::ToneMap { .. } => 17,
+        }
+    }
+
+    /// Parameters of the conversion as passed to `convert.frag`
+    pub fn params(&self) -> Vector4<f32> {
+        match *self {
+            Conversion::XyzToLab { white } | Conversion::LabToXyz { white } => white.extend(0.0),
+            Conversion::Encode(transfer) | Conversion::Decode(transfer) => {
+                Vector4::new(transfer.id() as f32, transfer.param(), 0.0, 0.0)
+            }
+            Conversion::Brettel { deficiency, severity } => {
+                Vector4::new(deficiency.index() as f32, severity, 0.0, 0.0)
+            }
+            Conversion::GamutMap(mapping) => {
+                let (id, param) = mapping.id_param();
+                Vector4::new(id as f32, param, 0.0, 0.0)
+            }
+            Conversion::GamutWarning { color } => color.extend(0.0),
+            Conversion::ToneMap { mapping, white } => Vector4::new(mapping.id() as f32, white, 0.0, 0.0),
+            _ => Vector4::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn apply(&self, c: Vector3<f32>) -> Vector3<f32> {
+        match *self {
+            Conversion::XyzToLab { white } => xyz_to_lab(c, white),
+            Conversion::LabToXyz { white } => lab_to_xyz(c, white),
+            Conversion::LabToLch => lab_to_lch(c),
+            Conversion::LchToLab => lch_to_lab(c),
+            Conversion::RgbToOklab => rgb_to_oklab(c),
+            Conversion::OklabToRgb => oklab_to_rgb(c),
+            Conversion::RgbToHsv => rgb_to_hsv(c),
+            Conversion::HsvToRgb => hsv_to_rgb(c),
+            Conversion::RgbToHsl => rgb_to_hsl(c),
+            Conversion::HslToRgb => hsl_to_rgb(c),
+            Conversion::RgbToHsi => rgb_to_hsi(c),
+            Conversion::HsiToRgb => hsi_to_rgb(c),
+            Conversion::Encode(transfer) => c.map(|c| transfer.encode(c)),
+            Conversion::Decode(transfer) => c.map(|c| transfer.decode(c)),
+            Conversion::Brettel { deficiency, severity } => cvd::brettel(c, deficiency, severity),
+            Conversion::GamutMap(mapping) => gamut::map(c, mapping),
+            Conversion::GamutWarning { color } => {
+                if gamut::in_gamut(c) {
+                    c
+                } else {
+                    color
+                }
+            }
+            Conversion::ToneMap { mapping, white } => tone::map(c, mapping, white),
+        }
+    }
+}
+
+/// Linear sRGB to CIE XYZ
+#[allow(clippy::unreadable_literal)]
+pub fn rgb_to_xyz_matrix() -> Matrix3<f32> {
+    Matrix3::new(
+        0.412453, 0.35758, 0.180423,
+        0.212671, 0.71516, 0.072169,
+        0.019334, 0.119193, 0.950227,
+    ).transpose()
+}
+
+/// Luminance Y of linear sRGB, the middle row of `rgb_to_xyz_matrix`
+pub fn luminance(rgb: Vector3<f32>) -> f32 {
+    rgb_to_xyz_matrix().row(1).dot(rgb)
+}
+
+/// CIE XYZ to linear sRGB
+#[allow(clippy::unreadable_literal)]
+pub fn xyz_to_rgb_matrix() -> Matrix3<f32> {
+    Matrix3::new(
+        3.240479, -1.53715, -0.498535,
+        -0.969256, 1.875991, 0.041556,
+        0.055648, -0.204043, 1.057311,
+    ).transpose()
+}
+
+/// CIE XYZ to LMS cone responses of Hunt-Pointer-Estevez
+pub fn xyz_to_lms_matrix() -> Matrix3<f32> {
+    Matrix3::new(
+        0.4002, 0.7076, -0.0808,
+        -0.2263, 1.1653, 0.0457,
+        0.0, 0.0, 0.9182,
+    ).transpose()
+}
+
+pub fn rgb_to_lms_matrix() -> Matrix3<f32> {
+    xyz_to_lms_matrix() * rgb_to_xyz_matrix()
+}
+
+pub fn lms_to_rgb_matrix() -> Matrix3<f32> {
+    rgb_to_lms_matrix().invert().unwrap()
+}
+
+/// Luma coefficients of the Y'CbCr standards
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum YCbCrStandard {
+    /// SDTV and JPEG
+    #[default]
+    Bt601,
+    /// HDTV
+    Bt709,
+    /// UHDTV
+    Bt2020,
+}
+
+impl YCbCrStandard {
+    /// Weights of red and blue in luma
+    fn kr_kb(self) -> (f32, f32) {
+        match self {
+            YCbCrStandard::Bt601 => (0.299, 0.114),
+            YCbCrStandard::Bt709 => (0.2126, 0.0722),
+            YCbCrStandard::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// Quantization range of Y'CbCr values
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Range {
+    /// Luma in [0, 1] and chroma in [0, 1] centered at 0.5
+    #[default]
+    Full,
+    /// Luma in [16, 235] / 255 and chroma in [16, 240] / 255 as in broadcast video
+    Limited,
+}
+
+/// Transform from encoded R'G'B' to Y'CbCr. Chroma is offset to be centered at gray.
+pub fn ycbcr_matrix(standard: YCbCrStandard, range: Range) -> Matrix4<f32> {
+    let (kr, kb) = standard.kr_kb();
+    let kg = 1.0 - kr - kb;
+    let cb = 0.5 / (1.0 - kb);
+    let cr = 0.5 / (1.0 - kr);
+    let to_ycbcr = Matrix4::new(
+        kr, kg, kb, 0.0,
+        -kr * cb, -kg * cb, (1.0 - kb) * cb, 0.5,
+        (1.0 - kr) * cr, -kg * cr, -kb * cr, 0.5,
+        0.0, 0.0, 0.0, 1.0,
+    ).transpose();
+    match range {
+        Range::Full => to_ycbcr,
+        Range::Limited => {
+            let (y, c) = (219.0 / 255.0, 224.0 / 255.0);
+            let quantize = Matrix4::new(
+                y, 0.0, 0.0, 16.0 / 255.0,
+                0.0, c, 0.0, 128.0 / 255.0 - 0.5 * c,
+                0.0, 0.0, c, 128.0 / 255.0 - 0.5 * c,
+                0.0, 0.0, 0.0, 1.0,
+            ).transpose();
+            quantize * to_ycbcr
+        }
+    }
+}
+
+/// Transform from encoded R'G'B' to YCoCg with chroma centered at 0.5
+pub fn ycocg_matrix() -> Matrix4<f32> {
+    Matrix4::new(
+        0.25, 0.5, 0.25, 0.0,
+        0.5, 0.0, -0.5, 0.5,
+        -0.25, 0.5, -0.25, 0.5,
+        0.0, 0.0, 0.0, 1.0,
+    ).transpose()
+}
+
+/// Chroma subsampling schemes of video codecs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subsampling {
+    /// Full resolution chroma
+    Chroma444,
+    /// Half horizontal chroma resolution
+    Chroma422,
+    /// Half horizontal and vertical chroma resolution
+    Chroma420,
+}
+
+impl Subsampling {
+    /// Size of the chroma planes of an image of size `(width, height)`
+    pub fn chroma_size(self, (width, height): (u32, u32)) -> (u32, u32) {
+        let half = |n: u32| n.div_ceil(2);
+        match self {
+            Subsampling::Chroma444 => (width, height),
+            Subsampling::Chroma422 => (half(width), height),
+            Subsampling::Chroma420 => (half(width), half(height)),
+        }
+    }
+}
+
+const LAB_DELTA: f32 = 6.0 / 29.0;
+
+fn lab_f(t: f32) -> f32 {
+    if t > LAB_DELTA.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * LAB_DELTA * LAB_DELTA) + 4.0 / 29.0
+    }
+}
+
+fn lab_f_inv(t: f32) -> f32 {
+    if t > LAB_DELTA {
+        t.powi(3)
+    } else {
+        3.0 * LAB_DELTA * LAB_DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+fn xyz_to_lab(xyz: Vector3<f32>, white: Vector3<f32>) -> Vector3<f32> {
+    let fx = lab_f(xyz.x / white.x);
+    let fy = lab_f(xyz.y / white.y);
+    let fz = lab_f(xyz.z / white.z);
+    Vector3::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+fn lab_to_xyz(lab: Vector3<f32>, white: Vector3<f32>) -> Vector3<f32> {
+    let fy = (lab.x + 16.0) / 116.0;
+    let fx = fy + lab.y / 500.0;
+    let fz = fy - lab.z / 200.0;
+    Vector3::new(
+        white.x * lab_f_inv(fx),
+        white.y * lab_f_inv(fy),
+        white.z * lab_f_inv(fz),
+    )
+}
+
+fn lab_to_lch(lab: Vector3<f32>) -> Vector3<f32> {
+    let c = (lab.y * lab.y + lab.z * lab.z).sqrt();
+    let mut h = lab.z.atan2(lab.y).to_degrees();
+    if h < 0.0 {
+        h += 360.0;
+    }
+    Vector3::new(lab.x, c, h)
+}
+
+fn lch_to_lab(lch: Vector3<f32>) -> Vector3<f32> {
+    let h = lch.z.to_radians();
+    Vector3::new(lch.x, lch.y * h.cos(), lch.y * h.sin())
+}
+
+#[allow(clippy::unreadable_literal, clippy::excessive_precision)]
+fn rgb_to_oklab(rgb: Vector3<f32>) -> Vector3<f32> {
+    let to_lms = Matrix3::new(
+        0.4122214708, 0.5363325363, 0.0514459929,
+        0.2119034982, 0.6806995451, 0.1073969566,
+        0.0883024619, 0.2817188376, 0.6299787005,
+    ).transpose();
+    let to_lab = Matrix3::new(
+        0.2104542553, 0.7936177850, -0.0040720468,
+        1.9779984951, -2.4285922050, 0.4505937099,
+        0.0259040371, 0.7827717662, -0.8086757660,
+    ).transpose();
+    to_lab * (to_lms * rgb).map(f32::cbrt)
+}
+
+#[allow(clippy::unreadable_literal, clippy::excessive_precision)]
+fn oklab_to_rgb(lab: Vector3<f32>) -> Vector3<f32> {
+    let to_lms = Matrix3::new(
+        1.0, 0.3963377774, 0.2158037573,
+        1.0, -0.1055613458, -0.0638541728,
+        1.0, -0.0894841775, -1.2914855480,
+    ).transpose();
+    let to_rgb = Matrix3::new(
+        4.0767416621, -3.3077115913, 0.2309699292,
+        -1.2684380046, 2.6097574011, -0.3413193965,
+        -0.0041960863, -0.7034186147, 1.7076147010,
+    ).transpose();
+    to_rgb * (to_lms * lab).map(|c| c * c * c)
+}
+
+fn max_component(c: Vector3<f32>) -> f32 {
+    c.x.max(c.y).max(c.z)
+}
+
+fn min_component(c: Vector3<f32>) -> f32 {
+    c.x.min(c.y).min(c.z)
+}
+
+/// Hue in degrees on the hexagon shared by HSV and HSL
+fn hexagonal_hue(rgb: Vector3<f32>) -> f32 {
+    let max = max_component(rgb);
+    let d = max - min_component(rgb);
+    if d == 0.0 {
+        0.0
+    } else if max == rgb.x {
+        60.0 * ((rgb.y - rgb.z) / d).rem_euclid(6.0)
+    } else if max == rgb.y {
+        60.0 * ((rgb.z - rgb.x) / d + 2.0)
+    } else {
+        60.0 * ((rgb.x - rgb.y) / d + 4.0)
+    }
+}
+
+fn rgb_to_hsv(rgb: Vector3<f32>) -> Vector3<f32> {
+    let max = max_component(rgb);
+    let d = max - min_component(rgb);
+    let s = if max > 0.0 { d / max } else { 0.0 };
+    Vector3::new(hexagonal_hue(rgb), s, max)
+}
+
+fn hsv_to_rgb(hsv: Vector3<f32>) -> Vector3<f32> {
+    let s = hsv.y.clamp(0.0, 1.0);
+    let f = |n: f32| {
+        let k = (n + hsv.x / 60.0).rem_euclid(6.0);
+        hsv.z - hsv.z * s * k.min(4.0 - k).clamp(0.0, 1.0)
+    };
+    Vector3::new(f(5.0), f(3.0), f(1.0))
+}
+
+fn rgb_to_hsl(rgb: Vector3<f32>) -> Vector3<f32> {
+    let max = max_component(rgb);
+    let min = min_component(rgb);
+    let l = (max + min) / 2.0;
+    let s = if l > 0.0 && l < 1.0 {
+        (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+    } else {
+        0.0
+    };
+    Vector3::new(hexagonal_hue(rgb), s, l)
+}
+
+fn hsl_to_rgb(hsl: Vector3<f32>) -> Vector3<f32> {
+    let a = hsl.y.clamp(0.0, 1.0) * hsl.z.min(1.0 - hsl.z);
+    let f = |n: f32| {
+        let k = (n + hsl.x / 30.0).rem_euclid(12.0);
+        hsl.z - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    Vector3::new(f(0.0), f(8.0), f(4.0))
+}
+
+/// HSI with the geometric hue of Gonzalez & Woods
+fn rgb_to_hsi(rgb: Vector3<f32>) -> Vector3<f32> {
+    let i = (rgb.x + rgb.y + rgb.z) / 3.0;
+    let s = if i > 0.0 { 1.0 - min_component(rgb) / i } else { 0.0 };
+    let num = 0.5 * ((rgb.x - rgb.y) + (rgb.x - rgb.z));
+    let den = ((rgb.x - rgb.y).powi(2) + (rgb.x - rgb.z) * (rgb.y - rgb.z)).sqrt();
+    let h = if den > 0.0 {
+        let theta = (num / den).clamp(-1.0, 1.0).acos().to_degrees();
+        if rgb.z > rgb.y { 360.0 - theta } else { theta }
+    } else {
+        0.0
+    };
+    Vector3::new(h, s, i)
+}
+
+fn hsi_to_rgb(hsi: Vector3<f32>) -> Vector3<f32> {
+    let h = hsi.x.rem_euclid(360.0);
+    let s = hsi.y.clamp(0.0, 1.0);
+    let i = hsi.z;
+    // Each 120 degree sector is the same formula with the channels rotated
+    let sector = (h / 120.0).floor().min(2.0);
+    let h = (h - 120.0 * sector).to_radians();
+    let low = i * (1.0 - s);
+    let high = i * (1.0 + s * h.cos() / (60f32.to_radians() - h).cos());
+    let rest = 3.0 * i - low - high;
+    match sector as i32 {
+        0 => Vector3::new(high, rest, low),
+        1 => Vector3::new(low, high, rest),
+        _ => Vector3::new(rest, low, high),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    use super::*;
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>, tolerance: f32) {
+        assert!((a - b).magnitude() < tolerance, "{:?} != {:?}", a, b);
+    }
+
+    /// Colors spread over the sRGB cube, with black, white and the primaries
+    fn test_colors() -> Vec<Vector3<f32>> {
+        let mut colors = vec![Vector3::zero(), Vector3::new(1.0, 1.0, 1.0)];
+        for i in 0..27usize {
+            let c = |i: usize| [0.05, 0.5, 0.95][i % 3];
+            colors.push(Vector3::new(c(i), c(i / 3), c(i / 9)));
+        }
+        colors.extend(&[Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()]);
+        colors
+    }
+
+    #[test]
+    fn lab_reference_values() {
+        let lab = |rgb| xyz_to_lab(rgb_to_xyz_matrix() * rgb, D65);
+        assert_close(lab(Vector3::new(1.0, 1.0, 1.0)), Vector3::new(100.0, 0.0, 0.0), 1e-3);
+        assert_close(lab(Vector3::new(1.0, 0.0, 0.0)), Vector3::new(53.2408, 80.0925, 67.2032), 1e-2);
+        assert_close(lab(Vector3::new(0.0, 0.0, 1.0)), Vector3::new(32.2970, 79.1875, -107.8602), 1e-2);
+        // The linear segment near black
+        assert_close(xyz_to_lab(D65 * 0.001, D65), Vector3::new(0.903_296, 0.0, 0.0), 1e-4);
+        assert_close(lab_to_lch(Vector3::new(50.0, 0.0, -20.0)), Vector3::new(50.0, 20.0, 270.0), 1e-4);
+    }
+
+    #[test]
+    fn lab_lch_round_trip() {
+        for rgb in test_colors() {
+            let xyz = rgb_to_xyz_matrix() * rgb;
+            let lab = xyz_to_lab(xyz, D65);
+            assert_close(lab_to_xyz(lab, D65), xyz, 1e-5);
+            let lch = lab_to_lch(lab);
+            assert!((0.0..360.0).contains(&lch.z), "{:?}", lch);
+            assert_close(lch_to_lab(lch), lab, 1e-3);
+        }
+    }
+
+    #[test]
+    fn oklab_reference_values() {
+        assert_close(rgb_to_oklab(Vector3::new(1.0, 1.0, 1.0)), Vector3::new(1.0, 0.0, 0.0), 1e-4);
+        assert_close(rgb_to_oklab(Vector3::new(1.0, 0.0, 0.0)), Vector3::new(0.627_955, 0.224_863, 0.125_846), 1e-4);
+        assert_close(rgb_to_oklab(Vector3::new(0.0, 1.0, 0.0)), Vector3::new(0.866_440, -0.233_888, 0.179_498), 1e-4);
+        assert_close(rgb_to_oklab(Vector3::new(0.0, 0.0, 1.0)), Vector3::new(0.452_014, -0.032_457, -0.311_528), 1e-4);
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        for rgb in test_colors() {
+            assert_close(oklab_to_rgb(rgb_to_oklab(rgb)), rgb, 1e-4);
+        }
+    }
+
+    #[test]
+    fn hue_spaces_reference_values() {
+        let orange = Vector3::new(1.0, 0.5, 0.0);
+        let steel = Vector3::new(0.2, 0.4, 0.6);
+        assert_close(rgb_to_hsv(orange), Vector3::new(30.0, 1.0, 1.0), 1e-4);
+        assert_close(rgb_to_hsv(steel), Vector3::new(210.0, 2.0 / 3.0, 0.6), 1e-4);
+        assert_close(rgb_to_hsl(orange), Vector3::new(30.0, 1.0, 0.5), 1e-4);
+        assert_close(rgb_to_hsl(steel), Vector3::new(210.0, 0.5, 0.4), 1e-4);
+        assert_close(rgb_to_hsi(Vector3::unit_y()), Vector3::new(120.0, 1.0, 1.0 / 3.0), 1e-4);
+        assert_close(rgb_to_hsi(Vector3::unit_z()), Vector3::new(240.0, 1.0, 1.0 / 3.0), 1e-4);
+        assert_close(rgb_to_hsi(steel), Vector3::new(210.0, 0.5, 0.4), 1e-3);
+        // Hue wraps around and saturation is clamped
+        assert_close(hsv_to_rgb(Vector3::new(390.0, 1.5, 1.0)), orange, 1e-4);
+        assert_close(hsl_to_rgb(Vector3::new(-330.0, 1.0, 0.5)), orange, 1e-4);
+    }
+
+    #[test]
+    fn hue_spaces_round_trip() {
+        for rgb in test_colors() {
+            assert_close(hsv_to_rgb(rgb_to_hsv(rgb)), rgb, 1e-5);
+            assert_close(hsl_to_rgb(rgb_to_hsl(rgb)), rgb, 1e-5);
+            assert_close(hsi_to_rgb(rgb_to_hsi(rgb)), rgb, 1e-4);
+        }
+    }
+
+    #[test]
+    fn ycbcr_reference_values() {
+        let ycbcr = |standard, range, rgb: Vector3<f32>| {
+            (ycbcr_matrix(standard, range) * rgb.extend(1.0)).truncate()
+        };
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let full = |rgb| ycbcr(YCbCrStandard::Bt601, Range::Full, rgb);
+        assert_close(full(white), Vector3::new(1.0, 0.5, 0.5), 1e-6);
+        assert_close(full(Vector3::unit_x()), Vector3::new(0.299, 0.331_264, 1.0), 1e-6);
+        let limited = |rgb| ycbcr(YCbCrStandard::Bt601, Range::Limited, rgb) * 255.0;
+        assert_close(limited(white), Vector3::new(235.0, 128.0, 128.0), 1e-4);
+        assert_close(limited(Vector3::zero()), Vector3::new(16.0, 128.0, 128.0), 1e-4);
+        assert_close(limited(Vector3::unit_z()), Vector3::new(40.966, 240.0, 109.786), 1e-3);
+        assert!((ycbcr(YCbCrStandard::Bt709, Range::Full, Vector3::unit_y()).x - 0.7152).abs() < 1e-6);
+        assert!((ycbcr(YCbCrStandard::Bt2020, Range::Full, Vector3::unit_y()).x - 0.678).abs() < 1e-6);
+        let ycocg = |rgb: Vector3<f32>| (ycocg_matrix() * rgb.extend(1.0)).truncate();
+        assert_close(ycocg(white), Vector3::new(1.0, 0.5, 0.5), 1e-6);
+        assert_close(ycocg(Vector3::unit_x()), Vector3::new(0.25, 1.0, 0.25), 1e-6);
+    }
+
+    #[test]
+    fn ycbcr_round_trip() {
+        let standards = [YCbCrStandard::Bt601, YCbCrStandard::Bt709, YCbCrStandard::Bt2020];
+        let mut matrices = vec![ycocg_matrix()];
+        for &standard in &standards {
+            matrices.push(ycbcr_matrix(standard, Range::Full));
+            matrices.push(ycbcr_matrix(standard, Range::Limited));
+        }
+        for m in matrices {
+            let inverse = m.invert().unwrap();
+            for rgb in test_colors() {
+                assert_close((inverse * (m * rgb.extend(1.0))).truncate(), rgb, 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn adaptation_maps_white_to_white() {
+        let methods = [AdaptationMethod::Bradford, AdaptationMethod::Cat02, AdaptationMethod::VonKries];
+        for &method in &methods {
+            for &from in &Illuminant::ALL {
+                for &to in &Illuminant::ALL {
+                    let adaptation = adaptation_matrix(from.white(), to.white(), method);
+                    assert_close(adaptation * from.white(), to.white(), 1e-5);
+                    let back = adaptation_matrix(to.white(), from.white(), method);
+                    let xyz = Vector3::new(0.3, 0.2, 0.5);
+                    assert_close(back * (adaptation * xyz), xyz, 1e-5);
+                }
+            }
+        }
+        // Bradford from D65 to D50 as tabulated by Lindbloom
+        let bradford = adaptation_matrix(D65, Illuminant::D50.white(), AdaptationMethod::Bradford);
+        let expected = Matrix3::new(
+            1.047_811, 0.022_887, -0.050_127,
+            0.029_542, 0.990_484, -0.017_049,
+            -0.009_234, 0.015_044, 0.752_132,
+        ).transpose();
+        for i in 0..3 {
+            assert_close(bradford[i], expected[i], 2e-4);
+        }
+    }
+
+    #[test]
+    fn illuminant_whites_match_their_spectra() {
+        for &illuminant in &Illuminant::ALL {
+            let white = Spectrum::illuminant(illuminant).white(Observer::Cie1931);
+            assert_close(white, illuminant.white(), 2e-3);
+        }
+    }
+
+    #[test]
+    fn srgb_matrices_match_the_shader() {
+        glsl::assert_matches("RGB_TO_XYZ", &glsl::rows(rgb_to_xyz_matrix()), 0.0);
+        glsl::assert_matches("XYZ_TO_RGB", &glsl::rows(xyz_to_rgb_matrix()), 0.0);
+        glsl::assert_matches("D65", &[D65.x, D65.y, D65.z], 0.0);
+        glsl::assert_matches("LAB_DELTA", &[LAB_DELTA], 0.0);
+        let rgb = Vector3::new(0.2, 0.5, 0.8);
+        assert_close(xyz_to_rgb_matrix() * (rgb_to_xyz_matrix() * rgb), rgb, 1e-5);
+    }
+}
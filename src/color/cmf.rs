@@ -0,0 +1,186 @@
+//! CIE standard observer color matching functions
+
+use cgmath::Vector3;
+
+use serde::Deserialize;
+
+/// Shortest tabulated wavelength in nm
+pub const WAVELENGTH_MIN: f32 = 380.0;
+/// Longest tabulated wavelength in nm
+pub const WAVELENGTH_MAX: f32 = 780.0;
+/// Spacing of the tables in nm
+pub const WAVELENGTH_STEP: f32 = 10.0;
+
+/// CIE 1931 2° x̄, ȳ, z̄ from 380 to 780 nm
+#[allow(clippy::unreadable_literal, clippy::excessive_precision)]
+const CIE_1931: [[f32; 3]; 41] = [
+    [0.001368, 0.000039, 0.006450],
+    [0.004243, 0.000120, 0.020050],
+    [0.014310, 0.000396, 0.067850],
+    [0.043510, 0.001210, 0.207400],
+    [0.134380, 0.004000, 0.645600],
+    [0.283900, 0.011600, 1.385600],
+    [0.348280, 0.023000, 1.747060],
+    [0.336200, 0.038000, 1.772110],
+    [0.290800, 0.060000, 1.669200],
+    [0.195360, 0.090980, 1.287640],
+    [0.095640, 0.139020, 0.812950],
+    [0.032010, 0.208020, 0.465180],
+    [0.004900, 0.323000, 0.272000],
+    [0.009300, 0.503000, 0.158200],
+    [0.063270, 0.710000, 0.078250],
+    [0.165500, 0.862000, 0.042160],
+    [0.290400, 0.954000, 0.020300],
+    [0.433450, 0.994950, 0.008750],
+    [0.594500, 0.995000, 0.003900],
+    [0.762100, 0.952000, 0.002100],
+    [0.916300, 0.870000, 0.001650],
+    [1.026300, 0.757000, 0.001100],
+    [1.062200, 0.631000, 0.000800],
+    [1.002600, 0.503000, 0.000340],
+    [0.854450, 0.381000, 0.000190],
+    [0.642400, 0.265000, 0.000050],
+    [0.447900, 0.175000, 0.000020],
+    [0.283500, 0.107000, 0.000000],
+    [0.164900, 0.061000, 0.000000],
+    [0.087400, 0.032000, 0.000000],
+    [0.046770, 0.017000, 0.000000],
+    [0.022700, 0.008210, 0.000000],
+    [0.011359, 0.004102, 0.000000],
+    [0.005790, 0.002091, 0.000000],
+    [0.002899, 0.001047, 0.000000],
+    [0.001440, 0.000520, 0.000000],
+    [0.000690, 0.000249, 0.000000],
+    [0.000332, 0.000120, 0.000000],
+    [0.000166, 0.000060, 0.000000],
+    [0.000083, 0.000030, 0.000000],
+    [0.000042, 0.000015, 0.000000],
+];
+
+/// CIE 1964 10° x̄, ȳ, z̄ from 380 to 780 nm
+#[allow(clippy::unreadable_literal, clippy::excessive_precision)]
+const CIE_1964: [[f32; 3]; 41] = [
+    [0.000160, 0.000017, 0.000705],
+    [0.002362, 0.000253, 0.010482],
+    [0.019110, 0.002004, 0.086011],
+    [0.084736, 0.008756, 0.389366],
+    [0.204492, 0.021391, 0.972542],
+    [0.314679, 0.038676, 1.553480],
+    [0.383734, 0.062077, 1.967280],
+    [0.370702, 0.089456, 1.994800],
+    [0.302273, 0.128201, 1.745370],
+    [0.195618, 0.185190, 1.317560],
+    [0.080507, 0.253589, 0.772125],
+    [0.016172, 0.339133, 0.415254],
+    [0.003816, 0.460777, 0.218502],
+    [0.037465, 0.606741, 0.112044],
+    [0.117749, 0.761757, 0.060709],
+    [0.236491, 0.875211, 0.030451],
+    [0.376772, 0.961988, 0.013676],
+    [0.529826, 0.991761, 0.003988],
+    [0.705224, 0.997340, 0.000000],
+    [0.878655, 0.955552, 0.000000],
+    [1.014160, 0.868934, 0.000000],
+    [1.118520, 0.777405, 0.000000],
+    [1.123990, 0.658341, 0.000000],
+    [1.030480, 0.527963, 0.000000],
+    [0.856297, 0.398057, 0.000000],
+    [0.647467, 0.283493, 0.000000],
+    [0.431567, 0.179828, 0.000000],
+    [0.268329, 0.107633, 0.000000],
+    [0.152568, 0.060281, 0.000000],
+    [0.081261, 0.031800, 0.000000],
+    [0.040851, 0.015905, 0.000000],
+    [0.019941, 0.007749, 0.000000],
+    [0.009577, 0.003718, 0.000000],
+    [0.004553, 0.001768, 0.000000],
+    [0.002175, 0.000846, 0.000000],
+    [0.001045, 0.000407, 0.000000],
+    [0.000508, 0.000199, 0.000000],
+    [0.000251, 0.000098, 0.000000],
+    [0.000126, 0.000050, 0.000000],
+    [0.000065, 0.000025, 0.000000],
+    [0.000033, 0.000013, 0.000000],
+];
+
+/// CIE standard colorimetric observer
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Observer {
+    /// 2° field of view
+    #[default]
+    Cie1931,
+    /// 10° field of view
+    Cie1964,
+}
+
+impl Observer {
+    /// x̄, ȳ and z̄ at `wavelength` in nm, interpolated with Catmull-Rom splines
+    /// and zero outside the table
+    pub fn cmf(self, wavelength: f32) -> Vector3<f32> {
+        let table = match self {
+            Observer::Cie1931 => &CIE_1931,
+            Observer::Cie1964 => &CIE_1964,
+        };
+        if !(WAVELENGTH_MIN..=WAVELENGTH_MAX).contains(&wavelength) {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let t = (wavelength - WAVELENGTH_MIN) / WAVELENGTH_STEP;
+        let i = (t.floor() as usize).min(table.len() - 2);
+        let f = t - i as f32;
+        let p = |j: usize| Vector3::from(table[j.clamp(1, table.len()) - 1]);
+        let (p0, p1, p2, p3) = (p(i), p(i + 1), p(i + 2), p(i + 3));
+        let c = p1
+            + ((p2 - p0)
+                + ((p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3)
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * f)
+                    * f)
+                * (0.5 * f);
+        // The spline overshoots below zero next to the zeros of z̄
+        c.map(|c| c.max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    use super::*;
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>, tolerance: f32) {
+        assert!((a - b).magnitude() < tolerance, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn tabulated_values() {
+        assert_eq!(Observer::Cie1931.cmf(550.0), Vector3::from(CIE_1931[17]));
+        assert_eq!(Observer::Cie1964.cmf(380.0), Vector3::from(CIE_1964[0]));
+        assert_close(Observer::Cie1931.cmf(780.0), Vector3::from(CIE_1931[40]), 1e-7);
+        assert_eq!(Observer::Cie1931.cmf(379.0), Vector3::zero());
+        assert_eq!(Observer::Cie1964.cmf(781.0), Vector3::zero());
+    }
+
+    #[test]
+    fn interpolates_the_5nm_tables() {
+        let cmf = |nm| Observer::Cie1931.cmf(nm);
+        assert_close(cmf(555.0), Vector3::new(0.512_05, 1.0, 0.005_75), 2e-3);
+        assert_close(cmf(505.0), Vector3::new(0.002_4, 0.407_3, 0.212_3), 5e-3);
+        assert_close(cmf(445.0), Vector3::new(0.348_28, 0.029_8, 1.782_6), 2e-2);
+        for observer in &[Observer::Cie1931, Observer::Cie1964] {
+            for nm in 380..=780 {
+                let c = observer.cmf(nm as f32);
+                assert!(c.x >= 0.0 && c.y >= 0.0 && c.z >= 0.0, "{} nm {:?}", nm, c);
+            }
+        }
+    }
+
+    #[test]
+    fn equal_energy_is_white() {
+        for table in &[CIE_1931, CIE_1964] {
+            let sum = table.iter().fold(Vector3::zero(), |sum, &c| sum + Vector3::from(c));
+            assert!((sum.x / sum.y - 1.0).abs() < 2e-3, "{:?}", sum);
+            assert!((sum.z / sum.y - 1.0).abs() < 2e-3, "{:?}", sum);
+        }
+    }
+}
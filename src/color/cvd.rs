@@ -0,0 +1,330 @@
+//! Simulation of dichromacy and anomalous trichromacy
+
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Vector3};
+
+use serde::Deserialize;
+
+use super::rgb_to_xyz_matrix;
+
+/// Missing or anomalous cone type
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Deficiency {
+    /// L cones
+    Protan,
+    /// M cones
+    Deutan,
+    /// S cones
+    Tritan,
+}
+
+impl Deficiency {
+    pub const ALL: [Deficiency; 3] = [Deficiency::Protan, Deficiency::Deutan, Deficiency::Tritan];
+
+    /// Index of the deficiency in `convert.frag`
+    pub fn index(self) -> usize {
+        match self {
+            Deficiency::Protan => 0,
+            Deficiency::Deutan => 1,
+            Deficiency::Tritan => 2,
+        }
+    }
+}
+
+/// Model of the color vision of a person with a deficiency
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CvdModel {
+    /// Brettel, Viénot & Mollon 1997. Projects onto two half planes in LMS.
+    #[default]
+    Brettel,
+    /// Viénot, Brettel & Mollon 1999. Projects onto a single plane through white
+    /// and blue, or red for tritan, which is less accurate for tritanopia.
+    Vienot,
+    /// Machado, Oliveira & Fernandes 2009
+    Machado,
+}
+
+/// Matrices of the two half planes of Brettel 1997 in linear sRGB and the normal of
+/// the plane that separates them, as computed by libDaltonLens
+#[allow(clippy::unreadable_literal)]
+const BRETTEL: [([f32; 9], [f32; 9], [f32; 3]); 3] = [
+    (
+        [0.14980, 1.19548, -0.34528, 0.10764, 0.84864, 0.04372, 0.00384, -0.00540, 1.00156],
+        [0.14570, 1.16172, -0.30742, 0.10816, 0.85291, 0.03892, 0.00386, -0.00524, 1.00139],
+        [0.00048, 0.00393, -0.00441],
+    ),
+    (
+        [0.36477, 0.86381, -0.22858, 0.26294, 0.64245, 0.09462, -0.02006, 0.02728, 0.99278],
+        [0.37298, 0.88166, -0.25464, 0.25954, 0.63506, 0.10540, -0.01980, 0.02784, 0.99196],
+        [-0.00281, -0.00611, 0.00892],
+    ),
+    (
+        [1.01277, 0.13548, -0.14826, -0.01243, 0.86812, 0.14431, 0.07589, 0.80500, 0.11911],
+        [0.93678, 0.18979, -0.12657, 0.06154, 0.81526, 0.12320, -0.37562, 1.12767, 0.24796],
+        [0.03901, -0.02788, -0.01113],
+    ),
+];
+
+/// Machado 2009 simulation matrices in linear sRGB for severities 0, 0.1, ..., 1,
+/// as published with the paper
+#[allow(clippy::unreadable_literal)]
+const MACHADO: [[[f32; 9]; 11]; 3] = [
+    // Protan
+    [
+        [1.000000, 0.000000, 0.000000, 0.000000, 1.000000, 0.000000, 0.000000, 0.000000, 1.000000],
+        [0.856167, 0.182038, -0.038205, 0.029342, 0.955115, 0.015544, -0.002880, -0.001563, 1.004443],
+        [0.734766, 0.334872, -0.069637, 0.051840, 0.919198, 0.028963, -0.004928, -0.004209, 1.009137],
+        [0.630323, 0.465641, -0.095964, 0.069181, 0.890046, 0.040773, -0.006308, -0.007724, 1.014032],
+        [0.539009, 0.579343, -0.118352, 0.082546, 0.866121, 0.051332, -0.007136, -0.011959, 1.019095],
+        [0.458064, 0.679578, -0.137642, 0.092785, 0.846313, 0.060902, -0.007494, -0.016807, 1.024301],
+        [0.385450, 0.769005, -0.154455, 0.100526, 0.829802, 0.069673, -0.007442, -0.022190, 1.029632],
+        [0.319627, 0.849633, -0.169261, 0.106241, 0.815969, 0.077790, -0.007025, -0.028051, 1.035076],
+        [0.259411, 0.923008, -0.182420, 0.110296, 0.804340, 0.085364, -0.006276, -0.034346, 1.040622],
+        [0.203876, 0.990338, -0.194214, 0.112975, 0.794542, 0.092483, -0.005222, -0.041043, 1.046265],
+        [0.152286, 1.052583, -0.204868, 0.114503, 0.786281, 0.099216, -0.003882, -0.048116, 1.051998],
+    ],
+    // Deutan
+    [
+        [1.000000, 0.000000, 0.000000, 0.000000, 1.000000, 0.000000, 0.000000, 0.000000, 1.000000],
+        [0.866435, 0.177704, -0.044139, 0.049567, 0.939063, 0.011370, -0.003453, 0.007233, 0.996220],
+        [0.760729, 0.319078, -0.079807, 0.090568, 0.889315, 0.020117, -0.006027, 0.013325, 0.992702],
+        [0.675425, 0.433850, -0.109275, 0.125303, 0.847755, 0.026942, -0.007950, 0.018572, 0.989378],
+        [0.605511, 0.528560, -0.134071, 0.155318, 0.812366, 0.032316, -0.009376, 0.023176, 0.986200],
+        [0.547494, 0.607765, -0.155259, 0.181692, 0.781742, 0.036566, -0.010410, 0.027275, 0.983136],
+        [0.498864, 0.674741, -0.173604, 0.205199, 0.754872, 0.039929, -0.011131, 0.030969, 0.980162],
+        [0.457771, 0.731899, -0.189670, 0.226409, 0.731012, 0.042579, -0.011595, 0.034333, 0.977261],
+        [0.422823, 0.781057, -0.203881, 0.245752, 0.709602, 0.044646, -0.011843, 0.037423, 0.974421],
+        [0.392952, 0.823610, -0.216562, 0.263559, 0.690210, 0.046232, -0.011910, 0.040281, 0.971630],
+        [0.367322, 0.860646, -0.227968, 0.280085, 0.672501, 0.047413, -0.011820, 0.042940, 0.968881],
+    ],
+    // Tritan
+    [
+        [1.000000, 0.000000, 0.000000, 0.000000, 1.000000, 0.000000, 0.000000, 0.000000, 1.000000],
+        [0.926670, 0.092514, -0.019184, 0.021191, 0.964503, 0.014306, 0.008437, 0.054813, 0.936750],
+        [0.895720, 0.133330, -0.029050, 0.029997, 0.945400, 0.024603, 0.013027, 0.104707, 0.882266],
+        [0.905871, 0.127791, -0.033662, 0.026856, 0.941251, 0.031893, 0.013410, 0.148296, 0.838294],
+        [0.948035, 0.089490, -0.037526, 0.014364, 0.946792, 0.038844, 0.010853, 0.193991, 0.795156],
+        [1.017277, 0.027029, -0.044306, -0.006113, 0.958479, 0.047634, 0.006379, 0.248708, 0.744913],
+        [1.104996, -0.046633, -0.058363, -0.032137, 0.971635, 0.060503, 0.001336, 0.317922, 0.680742],
+        [1.193214, -0.109812, -0.083402, -0.058496, 0.979410, 0.079086, -0.002346, 0.403492, 0.598854],
+        [1.257728, -0.139648, -0.118081, -0.078003, 0.975409, 0.102594, -0.003316, 0.501214, 0.502102],
+        [1.278864, -0.125333, -0.153531, -0.084748, 0.957674, 0.127074, -0.000989, 0.601151, 0.399838],
+        [1.255528, -0.076749, -0.178779, -0.078411, 0.930809, 0.147602, 0.004733, 0.691367, 0.303900],
+    ],
+];
+
+/// Row major `m` as a matrix that multiplies column vectors
+fn matrix(m: &[f32; 9]) -> Matrix3<f32> {
+    Matrix3::new(m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8]).transpose()
+}
+
+/// Simulate `deficiency` with Brettel 1997, blending with the input by `severity`
+pub fn brettel(rgb: Vector3<f32>, deficiency: Deficiency, severity: f32) -> Vector3<f32> {
+    let (h1, h2, normal) = &BRETTEL[deficiency.index()];
+    let h = if rgb.dot(Vector3::from(*normal)) >= 0.0 { h1 } else { h2 };
+    rgb.lerp(matrix(h) * rgb, severity)
+}
+
+/// Blend between normal vision at severity 0 and `simulation` at severity 1
+fn with_severity(simulation: Matrix3<f32>, severity: f32) -> Matrix3<f32> {
+    Matrix3::identity() * (1.0 - severity) + simulation * severity
+}
+
+/// CIE XYZ to the cone fundamentals of Smith & Pokorny 1975, which Viénot 1999
+/// is defined with. The paper converts from the Judd-Vos corrected XYZ, which is
+/// close to CIE 1931 XYZ for display primaries.
+fn xyz_to_smith_pokorny_matrix() -> Matrix3<f32> {
+    Matrix3::new(
+        0.15514, 0.54312, -0.03286,
+        -0.15514, 0.45684, 0.03286,
+        0.0, 0.0, 0.01608,
+    ).transpose()
+}
+
+/// Viénot 1999 simulation of `deficiency` as a linear rgb transform
+pub fn vienot_matrix(deficiency: Deficiency, severity: f32) -> Matrix3<f32> {
+    let to_lms = xyz_to_smith_pokorny_matrix() * rgb_to_xyz_matrix();
+    let white = to_lms * Vector3::new(1.0, 1.0, 1.0);
+    let anchor = match deficiency {
+        Deficiency::Protan | Deficiency::Deutan => to_lms * Vector3::new(0.0, 0.0, 1.0),
+        Deficiency::Tritan => to_lms * Vector3::new(1.0, 0.0, 0.0),
+    };
+    // The missing cone response is the combination of the other two that keeps
+    // white and the anchor unchanged
+    let (missing, a, b) = match deficiency {
+        Deficiency::Protan => (0, 1, 2),
+        Deficiency::Deutan => (1, 0, 2),
+        Deficiency::Tritan => (2, 0, 1),
+    };
+    let det = white[a] * anchor[b] - white[b] * anchor[a];
+    let wa = (white[missing] * anchor[b] - white[b] * anchor[missing]) / det;
+    let wb = (white[a] * anchor[missing] - white[missing] * anchor[a]) / det;
+    let mut projection = Matrix3::identity();
+    projection[missing][missing] = 0.0;
+    projection[a][missing] = wa;
+    projection[b][missing] = wb;
+    with_severity(to_lms.invert().unwrap() * projection * to_lms, severity)
+}
+
+/// Machado 2009 simulation of `deficiency`, interpolating linearly between the
+/// tables of the two nearest severities
+pub fn machado_matrix(deficiency: Deficiency, severity: f32) -> Matrix3<f32> {
+    let tables = &MACHADO[deficiency.index()];
+    let steps = (tables.len() - 1) as f32;
+    let x = severity.clamp(0.0, 1.0) * steps;
+    let i = (x as usize).min(tables.len() - 2);
+    let t = x - i as f32;
+    matrix(&tables[i]) * (1.0 - t) + matrix(&tables[i + 1]) * t
+}
+
+/// Daltonization of Fidaner et al. as a linear rgb transform. The difference between
+/// the image and its Viénot simulation is invisible to the viewer and is moved into
+/// the channels they can still tell apart, scaled by `strength`.
+pub fn daltonize_matrix(deficiency: Deficiency, strength: f32) -> Matrix3<f32> {
+    let error = Matrix3::identity() - vienot_matrix(deficiency, 1.0);
+    let redistribution = match deficiency {
+        // Red-green error to green and blue. Moving it into red would leave it on the
+        // axis deuteranopes can't see either.
+        Deficiency::Protan | Deficiency::Deutan => {
+            Matrix3::new(0.0, 0.0, 0.0, 0.7, 1.0, 0.0, 0.7, 0.0, 1.0)
+        }
+        // Lost blue to red and green
+        Deficiency::Tritan => Matrix3::new(1.0, 0.0, 0.7, 0.0, 1.0, 0.7, 0.0, 0.0, 0.0),
+    }
+    .transpose();
+    Matrix3::identity() + redistribution * error * strength
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::{Matrix3, Vector3};
+
+    use super::{brettel, daltonize_matrix, machado_matrix, matrix, vienot_matrix, Deficiency, BRETTEL, MACHADO};
+    use crate::color::glsl;
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>, tolerance: f32) {
+        assert!((a - b).magnitude() < tolerance, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_matrix_close(a: Matrix3<f32>, b: Matrix3<f32>, tolerance: f32) {
+        for i in 0..3 {
+            assert_close(a[i], b[i], tolerance);
+        }
+    }
+
+    #[test]
+    fn machado_tables_keep_white() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        for tables in &MACHADO {
+            assert_eq!(matrix(&tables[0]), Matrix3::identity());
+            for table in tables.iter() {
+                assert_close(matrix(table) * white, white, 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn machado_interpolates_between_severities() {
+        let protan = &MACHADO[Deficiency::Protan.index()];
+        assert_matrix_close(machado_matrix(Deficiency::Protan, 0.0), Matrix3::identity(), 1e-6);
+        assert_matrix_close(machado_matrix(Deficiency::Protan, 0.5), matrix(&protan[5]), 1e-6);
+        assert_matrix_close(machado_matrix(Deficiency::Protan, 1.0), matrix(&protan[10]), 1e-6);
+        let between = (matrix(&protan[2]) + matrix(&protan[3])) * 0.5;
+        assert_matrix_close(machado_matrix(Deficiency::Protan, 0.25), between, 1e-6);
+        // Published value for severity 1
+        let red = machado_matrix(Deficiency::Protan, 1.0) * Vector3::new(1.0, 0.0, 0.0);
+        assert_close(red, Vector3::new(0.152_286, 0.114_503, -0.003_882), 1e-6);
+        let clamped = machado_matrix(Deficiency::Tritan, 2.0);
+        assert_matrix_close(clamped, machado_matrix(Deficiency::Tritan, 1.0), 1e-6);
+    }
+
+    #[test]
+    fn vienot_keeps_white_and_its_anchor() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        for &deficiency in &Deficiency::ALL {
+            let simulation = vienot_matrix(deficiency, 1.0);
+            let anchor = match deficiency {
+                Deficiency::Protan | Deficiency::Deutan => Vector3::new(0.0, 0.0, 1.0),
+                Deficiency::Tritan => Vector3::new(1.0, 0.0, 0.0),
+            };
+            assert_close(simulation * white, white, 1e-5);
+            assert_close(simulation * anchor, anchor, 1e-5);
+            // Simulated colors are already on the plane of the dichromat
+            assert_matrix_close(simulation * simulation, simulation, 1e-5);
+            assert_matrix_close(vienot_matrix(deficiency, 0.0), Matrix3::identity(), 1e-6);
+        }
+    }
+
+    #[test]
+    fn vienot_confuses_red_and_green() {
+        // Protanopes and deuteranopes see all colors as mixtures of yellow and blue,
+        // so red and green keep equal red and green components
+        for &deficiency in &[Deficiency::Protan, Deficiency::Deutan] {
+            let simulation = vienot_matrix(deficiency, 1.0);
+            for &rgb in &[Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)] {
+                let seen = simulation * rgb;
+                assert!((seen.x - seen.y).abs() < 1e-5, "{:?} {:?}", deficiency, seen);
+            }
+        }
+    }
+
+    #[test]
+    fn daltonize_keeps_grays() {
+        for &deficiency in &Deficiency::ALL {
+            assert_matrix_close(daltonize_matrix(deficiency, 0.0), Matrix3::identity(), 1e-6);
+            let correction = daltonize_matrix(deficiency, 1.0);
+            for &gray in &[0.0, 0.18, 1.0] {
+                let gray = Vector3::new(gray, gray, gray);
+                assert_close(correction * gray, gray, 1e-5);
+            }
+            // The simulation of a dichromat sees no difference in the colors it leaves alone
+            let simulation = vienot_matrix(deficiency, 1.0);
+            let seen = Vector3::new(0.4, 0.4, 0.6);
+            assert_close(correction * simulation * seen, simulation * seen, 1e-5);
+        }
+    }
+
+    #[test]
+    fn daltonize_separates_confused_colors() {
+        let red = Vector3::new(0.8, 0.2, 0.2);
+        let green = Vector3::new(0.3, 0.6, 0.2);
+        let blue = Vector3::new(0.2, 0.3, 0.8);
+        let pairs = [
+            (Deficiency::Protan, red, green),
+            (Deficiency::Deutan, red, green),
+            (Deficiency::Tritan, green, blue),
+        ];
+        for &(deficiency, a, b) in &pairs {
+            let simulation = vienot_matrix(deficiency, 1.0);
+            let seen = |m: Matrix3<f32>| (simulation * m * a - simulation * m * b).magnitude();
+            let before = seen(Matrix3::identity());
+            let after = seen(daltonize_matrix(deficiency, 1.0));
+            assert!(after > before * 1.2, "{:?} {} {}", deficiency, before, after);
+        }
+    }
+
+    #[test]
+    fn brettel_keeps_white() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        let color = Vector3::new(0.8, 0.3, 0.1);
+        for &deficiency in &Deficiency::ALL {
+            assert_close(brettel(white, deficiency, 1.0), white, 1e-3);
+            assert_close(brettel(color, deficiency, 0.0), color, 1e-6);
+            let simulated = brettel(color, deficiency, 1.0);
+            assert_close(brettel(color, deficiency, 0.5), (color + simulated) * 0.5, 1e-6);
+        }
+    }
+
+    #[test]
+    fn brettel_matches_the_shader() {
+        let h1: Vec<f32> = BRETTEL.iter().flat_map(|(h1, _, _)| h1.to_vec()).collect();
+        let h2: Vec<f32> = BRETTEL.iter().flat_map(|(_, h2, _)| h2.to_vec()).collect();
+        let normals: Vec<f32> = BRETTEL.iter().flat_map(|(_, _, normal)| normal.to_vec()).collect();
+        glsl::assert_matches("BRETTEL_H1", &h1, 0.0);
+        glsl::assert_matches("BRETTEL_H2", &h2, 0.0);
+        glsl::assert_matches("BRETTEL_NORMAL", &normals, 0.0);
+    }
+}
@@ -0,0 +1,221 @@
+//! Detection and mapping of colors outside the [0, 1] rgb cube
+
+use cgmath::Vector3;
+
+use serde::Deserialize;
+
+use super::{lab_to_lch, lch_to_lab, lab_to_xyz, luminance, oklab_to_rgb, rgb_to_oklab, rgb_to_xyz_matrix,
+            xyz_to_lab, xyz_to_rgb_matrix, D65};
+
+/// Tolerance for rounding errors of in gamut colors
+const EPSILON: f32 = 1e-4;
+
+/// Perceptual space in which chroma is reduced at constant lightness and hue
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChromaSpace {
+    /// CIE L*C*h relative to D65
+    Lch,
+    Oklch,
+}
+
+/// Strategy to bring colors into the [0, 1] rgb cube
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GamutMapping {
+    /// Clamp each channel, which shifts hue and lightness
+    Clamp,
+    /// Move towards the gray of the same luminance until the color is in gamut
+    Clip,
+    /// Reduce chroma at constant lightness and hue until the color is in gamut
+    ReduceChroma(ChromaSpace),
+    /// Like `Clip`, but relative saturation above the knee in [0, 1) is compressed
+    /// smoothly so that in gamut colors close to the boundary also give way
+    SoftKnee(f32),
+}
+
+impl GamutMapping {
+    /// Identifier and parameter of the mapping in `convert.frag`
+    pub fn id_param(self) -> (i32, f32) {
+        match self {
+            GamutMapping::Clamp => (0, 0.0),
+            GamutMapping::Clip => (1, 0.0),
+            GamutMapping::ReduceChroma(ChromaSpace::Lch) => (2, 0.0),
+            GamutMapping::ReduceChroma(ChromaSpace::Oklch) => (3, 0.0),
+            GamutMapping::SoftKnee(knee) => (4, knee),
+        }
+    }
+}
+
+pub fn in_gamut(rgb: Vector3<f32>) -> bool {
+    (0..3).all(|i| rgb[i] >= -EPSILON && rgb[i] <= 1.0 + EPSILON)
+}
+
+pub fn map(rgb: Vector3<f32>, mapping: GamutMapping) -> Vector3<f32> {
+    match mapping {
+        GamutMapping::Clamp => rgb.map(|c| c.clamp(0.0, 1.0)),
+        GamutMapping::Clip => soft_clip(rgb, 1.0),
+        GamutMapping::ReduceChroma(space) => reduce_chroma(rgb, space),
+        GamutMapping::SoftKnee(knee) => soft_clip(rgb, knee),
+    }
+}
+
+/// Scale the distance of `rgb` to the gray of the same luminance. Relative saturation,
+/// where 1 is the gamut boundary, is kept below `knee` and compressed above it.
+fn soft_clip(rgb: Vector3<f32>, knee: f32) -> Vector3<f32> {
+    let y = luminance(rgb).clamp(0.0, 1.0);
+    let gray = Vector3::new(y, y, y);
+    let d = rgb - gray;
+    // Largest scale of d that stays in the cube
+    let mut t_max = f32::INFINITY;
+    for i in 0..3 {
+        if d[i] > 0.0 {
+            t_max = t_max.min((1.0 - y) / d[i]);
+        } else if d[i] < 0.0 {
+            t_max = t_max.min(y / -d[i]);
+        }
+    }
+    // Grays and colors whose luminance is out of range
+    if t_max <= 0.0 || t_max.is_infinite() {
+        return gray;
+    }
+    let s = 1.0 / t_max;
+    let compressed = if s <= knee {
+        s
+    } else {
+        knee + (1.0 - knee) * (s - knee) / (1.0 - knee + s - knee)
+    };
+    gray + d * (compressed / s)
+}
+
+type ColorFn = fn(Vector3<f32>) -> Vector3<f32>;
+
+/// Iterations of the chroma search, enough for 8 bit output
+const CHROMA_STEPS: usize = 16;
+
+/// Binary search for the largest chroma that is in gamut
+fn reduce_chroma(rgb: Vector3<f32>, space: ChromaSpace) -> Vector3<f32> {
+    if in_gamut(rgb) {
+        return rgb;
+    }
+    let (to_lch, to_rgb): (ColorFn, ColorFn) = match space {
+        ChromaSpace::Lch => (
+            |rgb| lab_to_lch(xyz_to_lab(rgb_to_xyz_matrix() * rgb, D65)),
+            |lch| xyz_to_rgb_matrix() * lab_to_xyz(lch_to_lab(lch), D65),
+        ),
+        ChromaSpace::Oklch => (
+            |rgb| lab_to_lch(rgb_to_oklab(rgb)),
+            |lch| oklab_to_rgb(lch_to_lab(lch)),
+        ),
+    };
+    let lch = to_lch(rgb);
+    // Lightness out of range can not be fixed by chroma
+    let white_l = to_lch(Vector3::new(1.0, 1.0, 1.0)).x;
+    let l = lch.x.clamp(0.0, white_l);
+    let (mut low, mut high) = (0.0, lch.y);
+    for _ in 0..CHROMA_STEPS {
+        let mid = 0.5 * (low + high);
+        if in_gamut(to_rgb(Vector3::new(l, mid, lch.z))) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    to_rgb(Vector3::new(l, low, lch.z)).map(|c| c.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::Vector3;
+
+    use super::*;
+    use crate::color::glsl;
+
+    const MAPPINGS: [GamutMapping; 5] = [
+        GamutMapping::Clamp,
+        GamutMapping::Clip,
+        GamutMapping::ReduceChroma(ChromaSpace::Lch),
+        GamutMapping::ReduceChroma(ChromaSpace::Oklch),
+        GamutMapping::SoftKnee(0.8),
+    ];
+
+    /// Linear sRGB of saturated colors of wider gamuts, with luminance in [0, 1]
+    fn out_of_gamut() -> Vec<Vector3<f32>> {
+        vec![
+            Vector3::new(-0.2, 1.05, -0.05),
+            Vector3::new(1.2, -0.1, -0.05),
+            Vector3::new(-0.1, -0.05, 1.1),
+            Vector3::new(0.9, 0.6, -0.3),
+        ]
+    }
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>, tolerance: f32) {
+        assert!((a - b).magnitude() < tolerance, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn in_gamut_colors_are_kept() {
+        let colors = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0), Vector3::new(0.4, 0.5, 0.3)];
+        for &mapping in &MAPPINGS {
+            for &rgb in &colors {
+                assert_close(map(rgb, mapping), rgb, 1e-5);
+            }
+        }
+        // Only the soft knee gives way inside the gamut
+        let saturated = Vector3::new(0.9, 0.1, 0.1);
+        assert_close(map(saturated, GamutMapping::Clip), saturated, 1e-6);
+        assert!(map(saturated, GamutMapping::SoftKnee(0.5)).x < 0.9);
+    }
+
+    #[test]
+    fn mapped_colors_are_in_gamut() {
+        for &mapping in &MAPPINGS {
+            for rgb in out_of_gamut() {
+                assert!(!in_gamut(rgb));
+                let mapped = map(rgb, mapping);
+                assert!(in_gamut(mapped), "{:?} maps {:?} to {:?}", mapping, rgb, mapped);
+            }
+        }
+        assert_eq!(map(Vector3::new(1.5, -0.5, 0.5), GamutMapping::Clamp), Vector3::new(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn clip_keeps_luminance_and_hue() {
+        for rgb in out_of_gamut() {
+            let mapped = map(rgb, GamutMapping::Clip);
+            assert!((luminance(mapped) - luminance(rgb)).abs() < 1e-5);
+            // On the line through the gray of the same luminance
+            let y = luminance(rgb);
+            let gray = Vector3::new(y, y, y);
+            let (d, dm) = (rgb - gray, mapped - gray);
+            assert!(d.normalize().dot(dm.normalize()) > 1.0 - 1e-5);
+            // The boundary, which the soft knee approaches from inside
+            assert!(dm.magnitude() <= d.magnitude());
+            let soft = map(rgb, GamutMapping::SoftKnee(0.8)) - gray;
+            assert!(soft.magnitude() < dm.magnitude());
+        }
+    }
+
+    #[test]
+    fn reduce_chroma_keeps_lightness_and_hue() {
+        for rgb in out_of_gamut() {
+            let lch = lab_to_lch(rgb_to_oklab(rgb));
+            let mapped = lab_to_lch(rgb_to_oklab(map(rgb, GamutMapping::ReduceChroma(ChromaSpace::Oklch))));
+            assert!((mapped.x - lch.x).abs() < 1e-3, "{:?} {:?}", lch, mapped);
+            assert!((mapped.z - lch.z).abs() < 0.5, "{:?} {:?}", lch, mapped);
+            assert!(mapped.y < lch.y);
+            let lab = |rgb| lab_to_lch(xyz_to_lab(rgb_to_xyz_matrix() * rgb, D65));
+            let (lch, mapped) = (lab(rgb), lab(map(rgb, GamutMapping::ReduceChroma(ChromaSpace::Lch))));
+            assert!((mapped.x - lch.x).abs() < 0.1, "{:?} {:?}", lch, mapped);
+            assert!((mapped.z - lch.z).abs() < 0.5, "{:?} {:?}", lch, mapped);
+            assert!(mapped.y < lch.y);
+        }
+    }
+
+    #[test]
+    fn constants_match_the_shader() {
+        glsl::assert_matches("GAMUT_EPSILON", &[EPSILON], 0.0);
+        glsl::assert_matches("CHROMA_STEPS", &[CHROMA_STEPS as f32], 0.0);
+    }
+}
@@ -0,0 +1,82 @@
+//! Constants of `convert.frag` for tests that check them against the Rust values.
+//! Matrices are written row by row in the shader, and so are the values returned
+//! for them here.
+
+use cgmath::Matrix3;
+
+const CONVERT: &str = include_str!("../shaders/convert.frag");
+
+/// Numbers in the initializer of the constant `name`. Initializers that are a
+/// product or quotient of literals evaluate to their single value.
+pub fn constant(name: &str) -> Vec<f32> {
+    let start = CONVERT
+        .lines()
+        .scan(0, |offset, line| {
+            let line_offset = *offset;
+            *offset += line.len() + 1;
+            Some((line_offset, line))
+        })
+        .find(|(_, line)| {
+            // Arrays have their size after the name
+            let declared = line.split_whitespace().nth(2).and_then(|name| name.split('[').next());
+            line.starts_with("const ") && declared == Some(name)
+        })
+        .map(|(offset, _)| offset)
+        .unwrap_or_else(|| panic!("No constant {} in convert.frag", name));
+    let statement = &CONVERT[start..start + CONVERT[start..].find(';').unwrap()];
+    let initializer = &statement[statement.find('=').unwrap() + 1..];
+    let numbers = literals(initializer);
+    if initializer.contains(['*', '/']) {
+        // Operators apply from left to right
+        let mut value = numbers[0];
+        for (op, number) in initializer.matches(['*', '/']).zip(&numbers[1..]) {
+            value = if op == "*" { value * number } else { value / number };
+        }
+        vec![value]
+    } else {
+        numbers
+    }
+}
+
+/// Literals outside of array sizes, skipping the digits of names like `mat3`
+fn literals(text: &str) -> Vec<f32> {
+    let mut numbers = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_alphabetic() || c == '_' {
+            while chars.peek().is_some_and(|&(_, c)| c.is_alphanumeric() || c == '_') {
+                chars.next();
+            }
+        } else if c == '[' {
+            while chars.next().is_some_and(|(_, c)| c != ']') {}
+        } else if c.is_ascii_digit() || (c == '-' && text[i + 1..].starts_with(char::is_numeric)) {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, c)) = chars.peek() {
+                let exponent_sign = (c == '-' || c == '+') && text[..j].ends_with('e');
+                if c.is_ascii_digit() || c == '.' || c == 'e' || exponent_sign {
+                    end = j + 1;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            numbers.push(text[i..end].parse().unwrap());
+        }
+    }
+    numbers
+}
+
+/// Values of `m` row by row, the order of the shader's transposed constructors
+pub fn rows(m: Matrix3<f32>) -> Vec<f32> {
+    (0..3).flat_map(|row| (0..3).map(move |col| m[col][row])).collect()
+}
+
+/// Check that the constant `name` differs from `rust` by at most `tolerance` in each value
+pub fn assert_matches(name: &str, rust: &[f32], tolerance: f32) {
+    let shader = constant(name);
+    assert_eq!(shader.len(), rust.len(), "{} has {:?} in convert.frag", name, shader);
+    for (s, r) in shader.iter().zip(rust) {
+        let message = format!("{} is {:?} in convert.frag and {:?} in Rust", name, shader, rust);
+        assert!((s - r).abs() <= tolerance, "{}", message);
+    }
+}
@@ -0,0 +1,381 @@
+//! ICC profiles with matrix and tone reproduction curves, the kind that describes
+//! displays and rgb working spaces like sRGB, Display P3 and Adobe RGB
+
+use cgmath::{Matrix3, Vector3};
+
+use super::{adaptation_matrix, AdaptationMethod, Illuminant, RgbColorSpace, TransferFunction, SRGB};
+
+/// Tone reproduction curve from encoded to linear values
+#[derive(Clone, Debug, PartialEq)]
+pub enum Curve {
+    Gamma(f32),
+    /// Values for equally spaced inputs from 0 to 1, interpolated linearly
+    Table(Vec<f32>),
+    /// Parametric curve with the parameters g, a, b, c, d, e and f of the ICC
+    /// `parametricCurveType`. Unused parameters make the pieces coincide.
+    Parametric([f32; 7]),
+}
+
+impl Curve {
+    pub fn decode(&self, c: f32) -> f32 {
+        let c = c.clamp(0.0, 1.0);
+        match self {
+            Curve::Gamma(gamma) => c.powf(*gamma),
+            Curve::Table(table) => {
+                let x = c * (table.len() - 1) as f32;
+                let i = (x as usize).min(table.len() - 2);
+                let t = x - i as f32;
+                table[i] * (1.0 - t) + table[i + 1] * t
+            }
+            Curve::Parametric([g, a, b, slope, d, e, f]) => {
+                if c >= *d {
+                    (a * c + b).max(0.0).powf(*g) + e
+                } else {
+                    slope * c + f
+                }
+            }
+        }
+    }
+}
+
+/// Profile of an rgb or gray device. Gray profiles have the same curve for all
+/// channels and a matrix that maps gray to the white point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IccProfile {
+    pub curves: [Curve; 3],
+    /// Linear device rgb to the D50 XYZ of the profile connection space
+    pub to_xyz: Matrix3<f32>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "Truncated profile".to_owned())
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "Truncated profile".to_owned())
+}
+
+fn s15_fixed16_at(data: &[u8], offset: usize) -> Result<f32, String> {
+    Ok(u32_at(data, offset)? as i32 as f32 / 65536.0)
+}
+
+fn s15_fixed16(value: f32) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+/// Number of parameters of each `parametricCurveType` function
+const PARAMETRIC_COUNTS: [usize; 5] = [1, 3, 4, 5, 7];
+
+impl IccProfile {
+    /// Read the curves and colorants of an rgb or gray profile. Profiles built
+    /// from lookup tables are not supported.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if data.get(36..40) != Some(b"acsp") {
+            return Err("Not an ICC profile".to_owned());
+        }
+        let n_tags = u32_at(data, 128)? as usize;
+        let tag = |signature: &[u8]| -> Result<&[u8], String> {
+            (0..n_tags)
+                .map(|i| 132 + 12 * i)
+                .find(|&entry| data.get(entry..entry + 4) == Some(signature))
+                .ok_or_else(|| format!("Missing tag {}", String::from_utf8_lossy(signature)))
+                .and_then(|entry| {
+                    let offset = u32_at(data, entry + 4)? as usize;
+                    let size = u32_at(data, entry + 8)? as usize;
+                    data.get(offset..offset + size)
+                        .filter(|tag| tag.len() >= 12)
+                        .ok_or_else(|| "Truncated profile".to_owned())
+                })
+        };
+        let xyz = |signature: &[u8]| -> Result<Vector3<f32>, String> {
+            let tag = tag(signature)?;
+            if &tag[..4] != b"XYZ " {
+                return Err("Invalid XYZ tag".to_owned());
+            }
+            Ok(Vector3::new(
+                s15_fixed16_at(tag, 8)?,
+                s15_fixed16_at(tag, 12)?,
+                s15_fixed16_at(tag, 16)?,
+            ))
+        };
+        match &data[16..20] {
+            b"RGB " => Ok(Self {
+                curves: [
+                    Self::curve(tag(b"rTRC")?)?,
+                    Self::curve(tag(b"gTRC")?)?,
+                    Self::curve(tag(b"bTRC")?)?,
+                ],
+                to_xyz: Matrix3::from_cols(xyz(b"rXYZ")?, xyz(b"gXYZ")?, xyz(b"bXYZ")?),
+            }),
+            b"GRAY" => {
+                let curve = Self::curve(tag(b"kTRC")?)?;
+                let white = Illuminant::D50.white() / 3.0;
+                Ok(Self {
+                    curves: [curve.clone(), curve.clone(), curve],
+                    to_xyz: Matrix3::from_cols(white, white, white),
+                })
+            }
+            space => Err(format!("Unsupported color space {}", String::from_utf8_lossy(space))),
+        }
+    }
+
+    fn curve(tag: &[u8]) -> Result<Curve, String> {
+        match &tag[..4] {
+            b"curv" => {
+                let count = u32_at(tag, 8)? as usize;
+                match count {
+                    0 => Ok(Curve::Gamma(1.0)),
+                    1 => Ok(Curve::Gamma(f32::from(u16_at(tag, 12)?) / 256.0)),
+                    _ => (0..count)
+                        .map(|i| Ok(f32::from(u16_at(tag, 12 + 2 * i)?) / 65535.0))
+                        .collect::<Result<_, String>>()
+                        .map(Curve::Table),
+                }
+            }
+            b"para" => {
+                let function = usize::from(u16_at(tag, 8)?);
+                let count = *PARAMETRIC_COUNTS
+                    .get(function)
+                    .ok_or_else(|| format!("Unknown parametric curve {}", function))?;
+                let mut p = [0.0; 7];
+                for (i, value) in p.iter_mut().enumerate().take(count) {
+                    *value = s15_fixed16_at(tag, 12 + 4 * i)?;
+                }
+                // Translate the functions to the most general one, type 4
+                let [g, a, b, c, ..] = p;
+                if (function == 1 || function == 2) && a == 0.0 {
+                    return Err(format!("Parametric curve {} with a zero slope", function));
+                }
+                Ok(Curve::Parametric(match function {
+                    0 => [g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                    1 => [g, a, b, 0.0, -b / a, 0.0, 0.0],
+                    2 => [g, a, b, 0.0, -b / a, c, c],
+                    _ => p,
+                }))
+            }
+            kind => Err(format!("Unsupported curve type {}", String::from_utf8_lossy(kind))),
+        }
+    }
+
+    /// Device rgb with the curves applied
+    pub fn linear(&self, rgb: Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(
+            self.curves[0].decode(rgb.x),
+            self.curves[1].decode(rgb.y),
+            self.curves[2].decode(rgb.z),
+        )
+    }
+
+    /// Linear device rgb to linear rgb of the working space, linear sRGB with the
+    /// white adapted from D50 with Bradford
+    pub fn working_space_matrix(&self) -> Matrix3<f32> {
+        let adapt = adaptation_matrix(Illuminant::D50.white(), SRGB.white_xyz(), AdaptationMethod::Bradford);
+        SRGB.xyz_to_rgb_matrix() * adapt * self.to_xyz
+    }
+
+    /// Version 4 display profile of `space` with its values encoded by
+    /// `transfer`, for embedding in saved files
+    pub fn write(space: &RgbColorSpace, transfer: TransferFunction, description: &str) -> Vec<u8> {
+        let d50 = Illuminant::D50.white();
+        let chad = adaptation_matrix(space.white_xyz(), d50, AdaptationMethod::Bradford);
+        let colorants = chad * space.rgb_to_xyz_matrix();
+
+        let xyz_tag = |xyz: Vector3<f32>| {
+            let mut tag = b"XYZ \0\0\0\0".to_vec();
+            for &c in &[xyz.x, xyz.y, xyz.z] {
+                tag.extend_from_slice(&s15_fixed16(c));
+            }
+            tag
+        };
+        let text_tag = |text: &str| {
+            // Multi-localized unicode with a single en-US record in UTF-16
+            let utf16: Vec<u8> = text.encode_utf16().flat_map(|c| c.to_be_bytes().to_vec()).collect();
+            let mut tag = b"mluc\0\0\0\0".to_vec();
+            tag.extend_from_slice(&1u32.to_be_bytes());
+            tag.extend_from_slice(&12u32.to_be_bytes());
+            tag.extend_from_slice(b"enUS");
+            tag.extend_from_slice(&(utf16.len() as u32).to_be_bytes());
+            tag.extend_from_slice(&28u32.to_be_bytes());
+            tag.extend_from_slice(&utf16);
+            tag
+        };
+        let mut chad_tag = b"sf32\0\0\0\0".to_vec();
+        for row in 0..3 {
+            for &c in &[chad.x[row], chad.y[row], chad.z[row]] {
+                chad_tag.extend_from_slice(&s15_fixed16(c));
+            }
+        }
+        let curve_tag = Self::curve_tag(transfer);
+
+        let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+            (b"desc", text_tag(description)),
+            (b"cprt", text_tag("No copyright, use freely")),
+            (b"wtpt", xyz_tag(d50)),
+            (b"chad", chad_tag),
+            (b"rXYZ", xyz_tag(colorants.x)),
+            (b"gXYZ", xyz_tag(colorants.y)),
+            (b"bXYZ", xyz_tag(colorants.z)),
+            (b"rTRC", curve_tag.clone()),
+            (b"gTRC", curve_tag.clone()),
+            (b"bTRC", curve_tag),
+        ];
+
+        // Tag data follows the tag table, with each tag aligned to 4 bytes
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut body = Vec::new();
+        let data_start = 128 + 4 + 12 * tags.len();
+        for (signature, tag) in &tags {
+            table.extend_from_slice(*signature);
+            table.extend_from_slice(&((data_start + body.len()) as u32).to_be_bytes());
+            table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+            body.extend_from_slice(tag);
+            while body.len() % 4 != 0 {
+                body.push(0);
+            }
+        }
+
+        let size = data_start + body.len();
+        let mut profile = Vec::with_capacity(size);
+        profile.extend_from_slice(&(size as u32).to_be_bytes());
+        profile.extend_from_slice(&[0; 4]);
+        // Version 4.3
+        profile.extend_from_slice(&[4, 0x30, 0, 0]);
+        profile.extend_from_slice(b"mntrRGB XYZ ");
+        // Creation date, left zero
+        profile.extend_from_slice(&[0; 12]);
+        profile.extend_from_slice(b"acsp");
+        // Platform, flags, manufacturer, model, attributes and perceptual intent
+        profile.extend_from_slice(&[0; 28]);
+        for &c in &[d50.x, d50.y, d50.z] {
+            profile.extend_from_slice(&s15_fixed16(c));
+        }
+        // Creator, profile id and reserved bytes
+        profile.resize(128, 0);
+        profile.extend_from_slice(&table);
+        profile.extend_from_slice(&body);
+        profile
+    }
+
+    fn curve_tag(transfer: TransferFunction) -> Vec<u8> {
+        let mut tag = Vec::new();
+        match transfer {
+            TransferFunction::Linear => tag.extend_from_slice(b"curv\0\0\0\0\0\0\0\0"),
+            TransferFunction::Gamma(gamma) => {
+                tag.extend_from_slice(b"curv\0\0\0\0");
+                tag.extend_from_slice(&1u32.to_be_bytes());
+                tag.extend_from_slice(&((gamma * 256.0).round() as u16).to_be_bytes());
+            }
+            TransferFunction::Srgb => {
+                tag.extend_from_slice(b"para\0\0\0\0");
+                tag.extend_from_slice(&3u16.to_be_bytes());
+                tag.extend_from_slice(&[0, 0]);
+                for &p in &[2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+                    tag.extend_from_slice(&s15_fixed16(p));
+                }
+            }
+            transfer => {
+                const SAMPLES: usize = 1024;
+                tag.extend_from_slice(b"curv\0\0\0\0");
+                tag.extend_from_slice(&(SAMPLES as u32).to_be_bytes());
+                for i in 0..SAMPLES {
+                    let linear = transfer.decode(i as f32 / (SAMPLES - 1) as f32);
+                    tag.extend_from_slice(&((linear.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes());
+                }
+            }
+        }
+        // Curves are padded to 4 bytes
+        while tag.len() % 4 != 0 {
+            tag.push(0);
+        }
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Matrix3, Vector3};
+
+    use super::{s15_fixed16, Curve, IccProfile};
+    use crate::color::{AdaptationMethod, RgbColorSpace, TransferFunction, SRGB};
+
+    fn assert_matrix_eq(a: Matrix3<f32>, b: Matrix3<f32>, tolerance: f32) {
+        for col in 0..3 {
+            for row in 0..3 {
+                assert!((a[col][row] - b[col][row]).abs() < tolerance, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    fn para(function: u16, params: &[f32]) -> Vec<u8> {
+        let mut tag = b"para\0\0\0\0".to_vec();
+        tag.extend_from_slice(&function.to_be_bytes());
+        tag.extend_from_slice(&[0, 0]);
+        for &p in params {
+            tag.extend_from_slice(&s15_fixed16(p));
+        }
+        tag
+    }
+
+    #[test]
+    fn write_parse_round_trip() {
+        let transfers = [
+            TransferFunction::Linear,
+            TransferFunction::Srgb,
+            TransferFunction::Gamma(2.2),
+            TransferFunction::Pq,
+        ];
+        for space in &RgbColorSpace::ALL {
+            for &transfer in &transfers {
+                let data = IccProfile::write(space, transfer, "Test");
+                assert_eq!(data.len() % 4, 0);
+                assert_eq!(u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize, data.len());
+                let profile = IccProfile::parse(&data).unwrap();
+                let expected = space.conversion_matrix(&SRGB, AdaptationMethod::Bradford);
+                assert_matrix_eq(profile.working_space_matrix(), expected, 1e-3);
+                for i in 0..=20 {
+                    let c = i as f32 / 20.0;
+                    let linear = profile.linear(Vector3::new(c, c, c));
+                    assert!((linear.x - transfer.decode(c)).abs() < 1e-3, "{:?} at {}", transfer, c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parametric_curves() {
+        let srgb_params = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+        let srgb = IccProfile::curve(&para(3, &srgb_params)).unwrap();
+        assert!((srgb.decode(0.5) - 0.214_041).abs() < 1e-4);
+        assert!((srgb.decode(0.02) - 0.02 / 12.92).abs() < 1e-5);
+        // Type 1 is zero below -b / a
+        let cie = IccProfile::curve(&para(1, &[2.0, 2.0, -0.5])).unwrap();
+        assert_eq!(cie.decode(0.2), 0.0);
+        assert!((cie.decode(0.75) - 1.0).abs() < 1e-5);
+        // Type 2 adds c everywhere
+        let offset = IccProfile::curve(&para(2, &[1.0, 1.0, 0.0, 0.25])).unwrap();
+        assert!((offset.decode(0.5) - 0.75).abs() < 1e-5);
+        let gamma = IccProfile::curve(&para(0, &[2.0])).unwrap();
+        assert_eq!(gamma, Curve::Parametric([2.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn invalid_curves_are_errors() {
+        assert!(IccProfile::curve(&para(1, &[2.2, 0.0, 0.1])).is_err());
+        assert!(IccProfile::curve(&para(2, &[2.2, 0.0, 0.1, 0.0])).is_err());
+        assert!(IccProfile::curve(&para(5, &[1.0])).is_err());
+        assert!(IccProfile::curve(&para(4, &[1.0])).is_err());
+        assert!(IccProfile::curve(b"mft2\0\0\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn not_a_profile() {
+        assert!(IccProfile::parse(b"not a profile").is_err());
+        let mut data = IccProfile::write(&SRGB, TransferFunction::Srgb, "Truncated");
+        data.truncate(200);
+        assert!(IccProfile::parse(&data).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! RGB color spaces defined by their primaries, white point and transfer function
+
+use std::convert::TryFrom;
+
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Vector3};
+
+use serde::de::{self, Deserialize, Deserializer};
+
+use super::{adaptation_matrix, AdaptationMethod, TransferFunction};
+
+/// D65 white point chromaticity
+const D65_XY: [f32; 2] = [0.3127, 0.3290];
+/// D50 white point chromaticity
+const D50_XY: [f32; 2] = [0.3457, 0.3585];
+
+/// RGB color space. The matrices to and from CIE XYZ are derived from the
+/// chromaticities of the primaries and the white point.
+///
+/// Deserializes from the names "srgb", "display_p3", "adobe_rgb", "rec2020" and "prophoto".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RgbColorSpace {
+    pub name: &'static str,
+    /// CIE xy of red, green and blue
+    pub primaries: [[f32; 2]; 3],
+    /// CIE xy of the white point
+    pub white: [f32; 2],
+    pub transfer: TransferFunction,
+}
+
+pub const SRGB: RgbColorSpace = RgbColorSpace {
+    name: "sRGB",
+    primaries: [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06]],
+    white: D65_XY,
+    transfer: TransferFunction::Srgb,
+};
+
+pub const DISPLAY_P3: RgbColorSpace = RgbColorSpace {
+    name: "Display P3",
+    primaries: [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060]],
+    white: D65_XY,
+    transfer: TransferFunction::Srgb,
+};
+
+pub const ADOBE_RGB: RgbColorSpace = RgbColorSpace {
+    name: "Adobe RGB (1998)",
+    primaries: [[0.64, 0.33], [0.21, 0.71], [0.15, 0.06]],
+    white: D65_XY,
+    transfer: TransferFunction::Gamma(563.0 / 256.0),
+};
+
+/// Rec. ITU-R BT.2020 on a BT.1886 display with a perfect black
+pub const REC2020: RgbColorSpace = RgbColorSpace {
+    name: "Rec. 2020",
+    primaries: [[0.708, 0.292], [0.170, 0.797], [0.131, 0.046]],
+    white: D65_XY,
+    transfer: TransferFunction::Bt1886(0.0),
+};
+
+/// ROMM RGB. The linear segment near black of the transfer function is ignored.
+pub const PROPHOTO: RgbColorSpace = RgbColorSpace {
+    name: "ProPhoto RGB",
+    primaries: [[0.7347, 0.2653], [0.1596, 0.8404], [0.0366, 0.0001]],
+    white: D50_XY,
+    transfer: TransferFunction::Gamma(1.8),
+};
+
+impl TryFrom<String> for RgbColorSpace {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        match name.as_str() {
+            "srgb" => Ok(SRGB),
+            "display_p3" => Ok(DISPLAY_P3),
+            "adobe_rgb" => Ok(ADOBE_RGB),
+            "rec2020" => Ok(REC2020),
+            "prophoto" => Ok(PROPHOTO),
+            _ => Err(format!("Unknown color space {}", name)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RgbColorSpace {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::try_from(String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// XYZ with Y = 1 of the chromaticity `xy`
+pub fn xy_to_xyz([x, y]: [f32; 2]) -> Vector3<f32> {
+    Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+impl RgbColorSpace {
+    pub const ALL: [RgbColorSpace; 5] = [SRGB, DISPLAY_P3, ADOBE_RGB, REC2020, PROPHOTO];
+
+    /// XYZ of the white point with Y = 1
+    pub fn white_xyz(&self) -> Vector3<f32> {
+        xy_to_xyz(self.white)
+    }
+
+    /// Linear rgb to CIE XYZ. The primaries are scaled so that rgb (1, 1, 1) is the white point.
+    pub fn rgb_to_xyz_matrix(&self) -> Matrix3<f32> {
+        let [r, g, b] = self.primaries;
+        let primaries = Matrix3::from_cols(xy_to_xyz(r), xy_to_xyz(g), xy_to_xyz(b));
+        let scale = primaries.invert().unwrap() * self.white_xyz();
+        Matrix3::from_cols(
+            primaries.x * scale.x,
+            primaries.y * scale.y,
+            primaries.z * scale.z,
+        )
+    }
+
+    pub fn xyz_to_rgb_matrix(&self) -> Matrix3<f32> {
+        self.rgb_to_xyz_matrix().invert().unwrap()
+    }
+
+    /// Linear rgb of this space to linear rgb of `to`. Different white points are
+    /// adapted with `method`.
+    pub fn conversion_matrix(&self, to: &RgbColorSpace, method: AdaptationMethod) -> Matrix3<f32> {
+        let adapt = adaptation_matrix(self.white_xyz(), to.white_xyz(), method);
+        to.xyz_to_rgb_matrix() * adapt * self.rgb_to_xyz_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::{Matrix3, Vector3};
+
+    use super::*;
+    use crate::color::rgb_to_xyz_matrix;
+
+    fn assert_matrix_close(a: Matrix3<f32>, b: Matrix3<f32>, tolerance: f32) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).magnitude() < tolerance, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn published_matrices() {
+        let srgb = Matrix3::new(
+            0.4124, 0.3576, 0.1805,
+            0.2126, 0.7152, 0.0722,
+            0.0193, 0.1192, 0.9505,
+        ).transpose();
+        assert_matrix_close(SRGB.rgb_to_xyz_matrix(), srgb, 2e-4);
+        // The fixed sRGB matrix of the shaders comes from a slightly different D65
+        assert_matrix_close(SRGB.rgb_to_xyz_matrix(), rgb_to_xyz_matrix(), 1e-3);
+        let p3 = Matrix3::new(
+            0.4866, 0.2657, 0.1982,
+            0.2290, 0.6917, 0.0793,
+            0.0000, 0.0451, 1.0439,
+        ).transpose();
+        assert_matrix_close(DISPLAY_P3.rgb_to_xyz_matrix(), p3, 2e-4);
+        // Normalized matrix of the Adobe RGB (1998) specification
+        let adobe = Matrix3::new(
+            0.57667, 0.18556, 0.18823,
+            0.29734, 0.62736, 0.07529,
+            0.02703, 0.07069, 0.99134,
+        ).transpose();
+        assert_matrix_close(ADOBE_RGB.rgb_to_xyz_matrix(), adobe, 2e-5);
+        let srgb_to_p3 = Matrix3::new(
+            0.822_462, 0.177_538, 0.0,
+            0.033_194, 0.966_806, 0.0,
+            0.017_083, 0.072_397, 0.910_520,
+        ).transpose();
+        let conversion = SRGB.conversion_matrix(&DISPLAY_P3, AdaptationMethod::Bradford);
+        assert_matrix_close(conversion, srgb_to_p3, 1e-5);
+    }
+
+    #[test]
+    fn white_maps_to_the_white_point() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        for space in &RgbColorSpace::ALL {
+            let xyz = space.rgb_to_xyz_matrix() * white;
+            assert!((xyz - space.white_xyz()).magnitude() < 1e-5, "{} {:?}", space.name, xyz);
+            let identity = space.xyz_to_rgb_matrix() * space.rgb_to_xyz_matrix();
+            assert_matrix_close(identity, Matrix3::identity(), 1e-5);
+        }
+    }
+
+    #[test]
+    fn conversion_round_trip() {
+        for from in &RgbColorSpace::ALL {
+            for to in &RgbColorSpace::ALL {
+                let there = from.conversion_matrix(to, AdaptationMethod::Bradford);
+                let back = to.conversion_matrix(from, AdaptationMethod::Bradford);
+                assert_matrix_close(back * there, Matrix3::identity(), 1e-4);
+                // Adaptation takes white to white, also between D65 and D50
+                let white = there * Vector3::new(1.0, 1.0, 1.0);
+                assert!((white - Vector3::new(1.0, 1.0, 1.0)).magnitude() < 1e-4, "{:?}", white);
+            }
+        }
+    }
+
+    #[test]
+    fn names() {
+        for (name, space) in &[("srgb", SRGB), ("display_p3", DISPLAY_P3), ("prophoto", PROPHOTO)] {
+            assert_eq!(RgbColorSpace::try_from(name.to_string()), Ok(*space));
+        }
+        assert!(RgbColorSpace::try_from("xyz".to_string()).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Spectral power distributions and reflectances
+
+use std::ops::{Add, Mul, Sub};
+
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Vector3};
+
+use serde::Deserialize;
+
+use super::cmf::WAVELENGTH_MIN;
+use super::{Illuminant, Observer};
+
+/// Spacing of the samples of a spectrum in nm
+pub const SPECTRUM_STEP: f32 = 5.0;
+/// Number of samples from 380 to 780 nm
+pub const SPECTRUM_SAMPLES: usize = 81;
+
+/// CIE daylight basis functions S0, S1 and S2 from 380 to 780 nm in 10 nm steps
+const DAYLIGHT: [[f32; 3]; 41] = [
+    [63.4, 38.5, 3.0], [65.8, 35.0, 1.2], [94.8, 43.4, -1.1], [104.8, 46.3, -0.5],
+    [105.9, 43.9, -0.7], [96.8, 37.1, -1.2], [113.9, 36.7, -2.6], [125.6, 35.9, -2.9],
+    [125.5, 32.6, -2.8], [121.3, 27.9, -2.6], [121.3, 24.3, -2.6], [113.5, 20.1, -1.8],
+    [113.1, 16.2, -1.5], [110.8, 13.2, -1.3], [106.5, 8.6, -1.2], [108.8, 6.1, -1.0],
+    [105.3, 4.2, -0.5], [104.4, 1.9, -0.3], [100.0, 0.0, 0.0], [96.0, -1.6, 0.2],
+    [95.1, -3.5, 0.5], [89.1, -3.5, 2.1], [90.5, -5.8, 3.2], [90.3, -7.2, 4.1],
+    [88.4, -8.6, 4.7], [84.0, -9.5, 5.1], [85.1, -10.9, 6.7], [81.9, -10.7, 7.3],
+    [82.6, -12.0, 8.6], [84.9, -14.0, 9.8], [81.3, -13.6, 10.2], [71.9, -12.0, 8.3],
+    [74.3, -13.3, 9.6], [76.4, -12.9, 8.5], [63.3, -10.6, 7.0], [71.7, -11.6, 7.6],
+    [77.0, -12.2, 8.0], [65.2, -10.2, 6.7], [47.7, -7.8, 5.2], [68.6, -11.2, 7.4],
+    [65.0, -10.4, 6.8],
+];
+
+/// CIE F2 cool white fluorescent from 380 to 780 nm in 5 nm steps
+const F2: [f32; SPECTRUM_SAMPLES] = [
+    1.18, 1.48, 1.84, 2.15, 3.44, 15.69, 3.85, 3.74, 4.19, 4.62,
+    5.06, 34.98, 11.81, 6.27, 6.63, 6.93, 7.19, 7.40, 7.54, 7.62,
+    7.65, 7.62, 7.62, 7.45, 7.28, 7.15, 7.05, 7.04, 7.16, 7.47,
+    8.04, 8.88, 10.01, 24.88, 16.64, 14.59, 16.16, 17.56, 18.62, 21.47,
+    22.79, 19.29, 18.66, 17.73, 16.54, 15.21, 13.80, 12.36, 10.95, 9.65,
+    8.40, 7.32, 6.31, 5.43, 4.68, 4.02, 3.45, 2.96, 2.55, 2.19,
+    1.89, 1.64, 1.53, 1.27, 1.10, 0.99, 0.88, 0.76, 0.68, 0.61,
+    0.56, 0.54, 0.51, 0.47, 0.47, 0.43, 0.46, 0.47, 0.40, 0.33,
+    0.27,
+];
+
+/// CIE F11 narrow band fluorescent from 380 to 780 nm in 5 nm steps
+const F11: [f32; SPECTRUM_SAMPLES] = [
+    0.91, 0.63, 0.46, 0.37, 1.29, 12.68, 1.59, 1.79, 2.46, 3.33,
+    4.49, 33.94, 12.13, 6.95, 7.19, 7.12, 6.72, 6.13, 5.46, 4.79,
+    5.66, 14.29, 14.96, 8.97, 4.72, 2.33, 1.47, 1.10, 0.89, 0.83,
+    1.18, 4.90, 39.59, 72.84, 32.61, 7.52, 2.83, 1.96, 1.67, 4.43,
+    11.28, 14.76, 12.73, 9.74, 7.33, 9.72, 55.27, 42.58, 13.18, 13.16,
+    12.26, 5.11, 2.07, 2.34, 3.58, 3.01, 2.48, 2.14, 1.54, 1.33,
+    1.46, 1.94, 2.00, 1.20, 1.35, 4.10, 5.58, 2.51, 0.57, 0.27,
+    0.23, 0.21, 0.24, 0.24, 0.20, 0.24, 0.32, 0.26, 0.16, 0.12,
+    0.09,
+];
+
+/// Light source of a spectral scene: `"D65"`, `{ blackbody = 3000 }` or `{ daylight = 7500 }`
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Light {
+    Standard(Illuminant),
+    /// Black body at a temperature in Kelvin
+    Blackbody { blackbody: f32 },
+    /// CIE daylight at a correlated color temperature in Kelvin
+    Daylight { daylight: f32 },
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light::Standard(Illuminant::D65)
+    }
+}
+
+impl Light {
+    pub fn spectrum(self) -> Spectrum {
+        match self {
+            Light::Standard(illuminant) => Spectrum::illuminant(illuminant),
+            Light::Blackbody { blackbody } => Spectrum::blackbody(blackbody),
+            Light::Daylight { daylight } => Spectrum::daylight(daylight),
+        }
+    }
+}
+
+/// Values sampled every `SPECTRUM_STEP` nm from 380 to 780 nm. Light sources are
+/// relative spectral power distributions and surfaces are reflectances in [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spectrum {
+    pub values: [f32; SPECTRUM_SAMPLES],
+}
+
+impl Spectrum {
+    /// Wavelength in nm of sample `i`
+    pub fn wavelength(i: usize) -> f32 {
+        WAVELENGTH_MIN + i as f32 * SPECTRUM_STEP
+    }
+
+    /// Sample `f` at each wavelength in nm
+    pub fn from_fn(f: impl Fn(f32) -> f32) -> Self {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = f(Self::wavelength(i));
+        }
+        Self { values }
+    }
+
+    /// Flat reflectance of a gray surface, or the equal energy illuminant E
+    pub fn constant(value: f32) -> Self {
+        Self::from_fn(|_| value)
+    }
+
+    /// Bell shaped reflectance peaking at `center` with standard deviation `width`
+    pub fn gaussian(center: f32, width: f32, peak: f32) -> Self {
+        Self::from_fn(|nm| peak * (-0.5 * ((nm - center) / width).powi(2)).exp())
+    }
+
+    /// Smooth step from 0 to 1 around `edge`, going down instead of up when
+    /// `width` is negative
+    pub fn sigmoid(edge: f32, width: f32) -> Self {
+        Self::from_fn(|nm| 1.0 / (1.0 + (-(nm - edge) / width).exp()))
+    }
+
+    /// Planck's law for a black body at `temperature` in Kelvin, normalized to
+    /// 100 at 560 nm
+    pub fn blackbody(temperature: f32) -> Self {
+        // Second radiation constant in nm K
+        const C2: f64 = 1.4388e7;
+        let t = f64::from(temperature);
+        let planck = |nm: f64| nm.powi(-5) / ((C2 / (nm * t)).exp() - 1.0);
+        let norm = 100.0 / planck(560.0);
+        Self::from_fn(|nm| (planck(f64::from(nm)) * norm) as f32)
+    }
+
+    /// CIE daylight at correlated color temperature `cct` in Kelvin from 4000 K
+    /// to 25000 K, normalized to 100 at 560 nm
+    pub fn daylight(cct: f32) -> Self {
+        let t = f64::from(cct);
+        let x = if t <= 7000.0 {
+            -4.6070e9 / t.powi(3) + 2.9678e6 / t.powi(2) + 0.09911e3 / t + 0.244_063
+        } else {
+            -2.0064e9 / t.powi(3) + 1.9018e6 / t.powi(2) + 0.24748e3 / t + 0.237_040
+        };
+        let y = -3.0 * x * x + 2.87 * x - 0.275;
+        let m = 0.0241 + 0.2562 * x - 0.7341 * y;
+        let m1 = ((-1.3515 - 1.7703 * x + 5.9114 * y) / m) as f32;
+        let m2 = ((0.0300 - 31.4424 * x + 30.0717 * y) / m) as f32;
+        // The basis is tabulated every 10 nm and interpolated linearly as the CIE recommends
+        Self::from_fn(|nm| {
+            let t = (nm - WAVELENGTH_MIN) / 10.0;
+            let i = (t as usize).min(DAYLIGHT.len() - 2);
+            let f = t - i as f32;
+            let s = Vector3::from(DAYLIGHT[i]).lerp(Vector3::from(DAYLIGHT[i + 1]), f);
+            s.x + m1 * s.y + m2 * s.z
+        })
+    }
+
+    /// Relative spectral power distribution of a standard illuminant
+    pub fn illuminant(illuminant: Illuminant) -> Self {
+        // The daylight temperatures are those of the 1931 constants, scaled to the
+        // current value of the second radiation constant
+        let scale = 1.4388 / 1.438;
+        match illuminant {
+            Illuminant::A => {
+                let planck = |nm: f32| 1.0 / ((1.435e7 / (2848.0 * nm)).exp() - 1.0);
+                Self::from_fn(|nm| 100.0 * (560.0 / nm).powi(5) * planck(nm) / planck(560.0))
+            }
+            Illuminant::D50 => Self::daylight(5000.0 * scale),
+            Illuminant::D55 => Self::daylight(5500.0 * scale),
+            Illuminant::D65 => Self::daylight(6500.0 * scale),
+            Illuminant::F2 => Self { values: F2 },
+            Illuminant::F11 => Self { values: F11 },
+        }
+    }
+
+    /// Observer color matching functions at the sample wavelengths
+    fn cmfs(observer: Observer) -> Vec<Vector3<f32>> {
+        (0..SPECTRUM_SAMPLES).map(|i| observer.cmf(Self::wavelength(i))).collect()
+    }
+
+    /// Tristimulus values of light with this power distribution
+    pub fn xyz(&self, observer: Observer) -> Vector3<f32> {
+        Self::cmfs(observer)
+            .iter()
+            .zip(self.values.iter())
+            .fold(Vector3::zero(), |sum, (&cmf, &value)| sum + cmf * value * SPECTRUM_STEP)
+    }
+
+    /// XYZ with Y = 1 of the white point of a light source
+    pub fn white(&self, observer: Observer) -> Vector3<f32> {
+        let xyz = self.xyz(observer);
+        xyz / xyz.y
+    }
+
+    /// XYZ of a surface with this reflectance lit by `illuminant`, with Y = 1 for
+    /// the perfect reflector
+    pub fn reflected(&self, illuminant: &Spectrum, observer: Observer) -> Vector3<f32> {
+        (*self * *illuminant).xyz(observer) / illuminant.xyz(observer).y
+    }
+
+    /// The part of `perturbation` that is invisible under `illuminant`: the component
+    /// orthogonal to the color matching functions weighted by the illuminant. Adding
+    /// it to a reflectance gives a metamer.
+    pub fn metameric_black(illuminant: &Spectrum, observer: Observer, perturbation: &Spectrum) -> Self {
+        let a: Vec<_> = Self::cmfs(observer)
+            .iter()
+            .zip(illuminant.values.iter())
+            .map(|(&cmf, &power)| cmf * power)
+            .collect();
+        // Least squares fit of the perturbation by the weighted matching functions
+        let ata = a
+            .iter()
+            .fold(Matrix3::zero(), |sum, &v| sum + Matrix3::from_cols(v * v.x, v * v.y, v * v.z));
+        let atp = a
+            .iter()
+            .zip(perturbation.values.iter())
+            .fold(Vector3::zero(), |sum, (&v, &p)| sum + v * p);
+        let coefficients = match ata.invert() {
+            Some(inverse) => inverse * atp,
+            None => return *perturbation,
+        };
+        let mut black = *perturbation;
+        for (value, v) in black.values.iter_mut().zip(a.iter()) {
+            *value -= v.dot(coefficients);
+        }
+        black
+    }
+
+    /// Reflectance with the same color under `illuminant` plus as much of the
+    /// metameric black of `perturbation` as fits in [0, 1]
+    pub fn metamer(&self, illuminant: &Spectrum, observer: Observer, perturbation: &Spectrum) -> Self {
+        let black = Self::metameric_black(illuminant, observer, perturbation);
+        let amount = self
+            .values
+            .iter()
+            .zip(black.values.iter())
+            .map(|(&r, &b)| {
+                if b > 0.0 {
+                    (1.0 - r) / b
+                } else if b < 0.0 {
+                    -r / b
+                } else {
+                    f32::INFINITY
+                }
+            })
+            .fold(f32::INFINITY, f32::min);
+        if amount.is_finite() {
+            *self + black * amount.max(0.0)
+        } else {
+            *self
+        }
+    }
+
+    pub fn map(mut self, f: impl Fn(f32) -> f32) -> Self {
+        for value in self.values.iter_mut() {
+            *value = f(*value);
+        }
+        self
+    }
+
+    fn zip_with(mut self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+        for (value, &other) in self.values.iter_mut().zip(other.values.iter()) {
+            *value = f(*value, other);
+        }
+        self
+    }
+}
+
+impl Add for Spectrum {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.zip_with(other, |a, b| a + b)
+    }
+}
+
+impl Sub for Spectrum {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.zip_with(other, |a, b| a - b)
+    }
+}
+
+/// Product of the values at each wavelength, like a reflectance lit by a light source
+impl Mul for Spectrum {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.zip_with(other, |a, b| a * b)
+    }
+}
+
+impl Mul<f32> for Spectrum {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        self.map(|value| value * factor)
+    }
+}
+
@@ -0,0 +1,191 @@
+//! Tone mapping of high dynamic range linear rgb into [0, 1]
+
+use cgmath::prelude::*;
+use cgmath::{Matrix3, Vector3};
+
+use serde::Deserialize;
+
+use super::luminance;
+
+/// Operator that compresses linear values above 1 into the displayable range
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMapping {
+    /// Reinhard 2002 on luminance, with the white point mapped to 1
+    Reinhard,
+    /// Reinhard with each pixel compressed by the luminance of its surroundings
+    /// instead of its own. Per pixel it is `Reinhard` without a white point.
+    ReinhardLocal,
+    /// Krzysztof Narkowicz's fit of the ACES filmic reference rendering
+    #[default]
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2, with the white point mapped to 1
+    Hable,
+    /// Troy Sobotka's AgX with the polynomial fit of its contrast curve
+    Agx,
+}
+
+impl ToneMapping {
+    /// Identifier of the operator in `convert.frag`
+    pub fn id(self) -> i32 {
+        match self {
+            ToneMapping::Reinhard => 0,
+            ToneMapping::Aces => 1,
+            ToneMapping::Hable => 2,
+            ToneMapping::Agx => 3,
+            ToneMapping::ReinhardLocal => 4,
+        }
+    }
+
+    /// Linear value mapped to 1 when no white point is given. ACES, AgX and local
+    /// Reinhard ignore the white point.
+    pub fn default_white(self) -> f32 {
+        match self {
+            // Hable's linear white of 11.2 before his exposure bias
+            ToneMapping::Hable => 5.6,
+            _ => 4.0,
+        }
+    }
+}
+
+/// Without a white point no value reaches 1
+fn reinhard(rgb: Vector3<f32>, white: Option<f32>) -> Vector3<f32> {
+    let l = luminance(rgb);
+    if l <= 0.0 {
+        return Vector3::zero();
+    }
+    let mapped = match white {
+        Some(white) => l * (1.0 + l / (white * white)) / (1.0 + l),
+        None => l / (1.0 + l),
+    };
+    rgb * (mapped / l)
+}
+
+fn aces(x: f32) -> f32 {
+    let x = x.max(0.0);
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+fn hable_curve(x: f32) -> f32 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+/// Hable's curve with his exposure bias of 2, which also applies to the white point
+/// so that `white` maps to 1
+fn hable(x: f32, white: f32) -> f32 {
+    hable_curve(2.0 * x.max(0.0)) / hable_curve(2.0 * white)
+}
+
+/// Log2 exposure range of the AgX base encoding around middle gray
+const AGX_MIN_EV: f32 = -12.473_93;
+const AGX_MAX_EV: f32 = 4.026_069;
+
+/// Inset of the primaries towards white before the log encoding
+#[allow(clippy::unreadable_literal, clippy::excessive_precision)]
+fn agx_inset() -> Matrix3<f32> {
+    Matrix3::new(
+        0.842479062253094, 0.0784335999999992, 0.0792237451477643,
+        0.0423282422610123, 0.878468636469772, 0.0791661274605434,
+        0.0423756549057051, 0.0784336, 0.879142973793104,
+    ).transpose()
+}
+
+#[allow(clippy::unreadable_literal, clippy::excessive_precision)]
+fn agx_outset() -> Matrix3<f32> {
+    Matrix3::new(
+        1.19687900512017, -0.0980208811401368, -0.0990297440797205,
+        -0.0528968517574562, 1.15190312990417, -0.0989611768448433,
+        -0.0529716355144438, -0.0980434501171241, 1.15107367264116,
+    ).transpose()
+}
+
+/// Polynomial fit of the AgX sigmoid on log encoded values in [0, 1]
+fn agx_contrast(x: f32) -> f32 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+}
+
+fn agx(rgb: Vector3<f32>) -> Vector3<f32> {
+    let inset = agx_inset() * rgb;
+    let curve = inset.map(|c| {
+        let ev = c.max(1e-10).log2().clamp(AGX_MIN_EV, AGX_MAX_EV);
+        agx_contrast((ev - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV))
+    });
+    // The curve produces display values with a 2.2 gamma
+    (agx_outset() * curve).map(|c| c.max(0.0).powf(2.2))
+}
+
+pub fn map(rgb: Vector3<f32>, mapping: ToneMapping, white: f32) -> Vector3<f32> {
+    match mapping {
+        ToneMapping::Reinhard => reinhard(rgb, Some(white)),
+        ToneMapping::ReinhardLocal => reinhard(rgb, None),
+        ToneMapping::Aces => rgb.map(aces),
+        ToneMapping::Hable => rgb.map(|c| hable(c, white)),
+        ToneMapping::Agx => agx(rgb),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::glsl;
+
+    const MAPPINGS: [ToneMapping; 5] = [
+        ToneMapping::Reinhard,
+        ToneMapping::ReinhardLocal,
+        ToneMapping::Aces,
+        ToneMapping::Hable,
+        ToneMapping::Agx,
+    ];
+
+    fn gray(c: f32) -> Vector3<f32> {
+        Vector3::new(c, c, c)
+    }
+
+    fn assert_close(a: f32, b: f32, tolerance: f32) {
+        assert!((a - b).abs() <= tolerance, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn reference_values() {
+        assert_close(map(gray(1.0), ToneMapping::Reinhard, 4.0).x, 0.531_25, 1e-5);
+        assert_close(map(gray(4.0), ToneMapping::Reinhard, 4.0).x, 1.0, 1e-5);
+        assert_close(map(gray(1.0), ToneMapping::ReinhardLocal, 4.0).x, 0.5, 1e-5);
+        assert_close(aces(0.18), 0.266_898, 1e-5);
+        assert_close(aces(100.0), 1.0, 1e-6);
+        assert_close(hable(5.6, 5.6), 1.0, 1e-6);
+        assert_close(hable(0.0, 5.6), 0.0, 1e-6);
+        assert_close(hable(1.0, 5.6), 0.492_919, 1e-5);
+        // AgX keeps grays neutral and lifts middle gray as its display encoding does
+        let agx = map(gray(0.18), ToneMapping::Agx, 4.0);
+        assert_close(agx.x, agx.y, 1e-3);
+        assert_close(agx.z, agx.y, 1e-3);
+        assert!(agx.y > 0.1 && agx.y < 0.3, "{:?}", agx);
+    }
+
+    #[test]
+    fn monotonic_into_the_unit_range() {
+        for &mapping in &MAPPINGS {
+            let white = mapping.default_white();
+            let mut previous = map(gray(0.0), mapping, white).y;
+            assert!(previous.abs() < 1e-2, "{:?} maps black to {}", mapping, previous);
+            for i in 1..=100 {
+                let c = i as f32 / 100.0 * white;
+                let mapped = map(gray(c), mapping, white).y;
+                assert!(mapped >= previous, "{:?} at {}", mapping, c);
+                assert!(mapped <= 1.0 + 1e-4, "{:?} maps {} to {}", mapping, c, mapped);
+                previous = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn agx_matches_the_shader() {
+        glsl::assert_matches("AGX_MIN_EV", &[AGX_MIN_EV], 0.0);
+        glsl::assert_matches("AGX_MAX_EV", &[AGX_MAX_EV], 0.0);
+        glsl::assert_matches("AGX_INSET", &glsl::rows(agx_inset()), 0.0);
+        glsl::assert_matches("AGX_OUTSET", &glsl::rows(agx_outset()), 0.0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Transfer functions between linear light and encoded signal values
+
+use serde::Deserialize;
+
+/// Encoding of the channel values of an RGB color space
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFunction {
+    Linear,
+    /// Piecewise IEC 61966-2-1 curve
+    #[default]
+    Srgb,
+    /// Pure power law, mirrored for negative values
+    Gamma(f32),
+    /// ITU-R BT.1886 display with gamma 2.4 and the given black level relative to white
+    Bt1886(f32),
+    /// SMPTE ST 2084 perceptual quantizer. Linear 1 is 10000 cd/m².
+    Pq,
+    /// ITU-R BT.2100 hybrid log-gamma OETF of relative scene light in [0, 1]
+    Hlg,
+}
+
+impl TransferFunction {
+    /// Identifier of the function in `convert.frag`
+    pub fn id(self) -> i32 {
+        match self {
+            TransferFunction::Linear => 0,
+            TransferFunction::Srgb => 1,
+            TransferFunction::Gamma(_) => 2,
+            TransferFunction::Bt1886(_) => 3,
+            TransferFunction::Pq => 4,
+            TransferFunction::Hlg => 5,
+        }
+    }
+
+    /// Parameter of the function as passed to `convert.frag`
+    pub fn param(self) -> f32 {
+        match self {
+            TransferFunction::Gamma(gamma) => gamma,
+            TransferFunction::Bt1886(black) => black,
+            TransferFunction::Linear
+            | TransferFunction::Srgb
+            | TransferFunction::Pq
+            | TransferFunction::Hlg => 0.0,
+        }
+    }
+
+    /// Linear to encoded
+    pub fn encode(self, c: f32) -> f32 {
+        match self {
+            TransferFunction::Linear => c,
+            TransferFunction::Srgb => srgb_encode(c),
+            TransferFunction::Gamma(gamma) => c.signum() * c.abs().powf(1.0 / gamma),
+            TransferFunction::Bt1886(black) => {
+                let (a, b) = bt1886_constants(black);
+                (c.max(0.0) / a).powf(1.0 / BT1886_GAMMA) - b
+            }
+            TransferFunction::Pq => pq_encode(c),
+            TransferFunction::Hlg => hlg_encode(c),
+        }
+    }
+
+    /// Encoded to linear
+    pub fn decode(self, c: f32) -> f32 {
+        match self {
+            TransferFunction::Linear => c,
+            TransferFunction::Srgb => srgb_decode(c),
+            TransferFunction::Gamma(gamma) => c.signum() * c.abs().powf(gamma),
+            TransferFunction::Bt1886(black) => {
+                let (a, b) = bt1886_constants(black);
+                a * (c + b).max(0.0).powf(BT1886_GAMMA)
+            }
+            TransferFunction::Pq => pq_decode(c),
+            TransferFunction::Hlg => hlg_decode(c),
+        }
+    }
+}
+
+/// IEC 61966-2-1 sRGB decoding, which is what the hardware uses for sRGB textures
+pub fn srgb_decode(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn srgb_encode(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+const BT1886_GAMMA: f32 = 2.4;
+
+/// Gain and black lift of the BT.1886 EOTF for white at 1
+fn bt1886_constants(black: f32) -> (f32, f32) {
+    let black = black.powf(1.0 / BT1886_GAMMA);
+    ((1.0 - black).powf(BT1886_GAMMA), black / (1.0 - black))
+}
+
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+fn pq_encode(c: f32) -> f32 {
+    let y = c.max(0.0).powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y)).powf(PQ_M2)
+}
+
+fn pq_decode(c: f32) -> f32 {
+    let p = c.max(0.0).powf(1.0 / PQ_M2);
+    ((p - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * p)).powf(1.0 / PQ_M1)
+}
+
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 1.0 - 4.0 * HLG_A;
+const HLG_C: f32 = 0.559_910_7;
+
+fn hlg_encode(c: f32) -> f32 {
+    let c = c.max(0.0);
+    if c <= 1.0 / 12.0 {
+        (3.0 * c).sqrt()
+    } else {
+        HLG_A * (12.0 * c - HLG_B).ln() + HLG_C
+    }
+}
+
+fn hlg_decode(c: f32) -> f32 {
+    let c = c.max(0.0);
+    if c <= 0.5 {
+        c * c / 3.0
+    } else {
+        (((c - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::glsl;
+
+    const FUNCTIONS: [TransferFunction; 7] = [
+        TransferFunction::Linear,
+        TransferFunction::Srgb,
+        TransferFunction::Gamma(2.2),
+        TransferFunction::Bt1886(0.0),
+        TransferFunction::Bt1886(0.01),
+        TransferFunction::Pq,
+        TransferFunction::Hlg,
+    ];
+
+    fn assert_close(a: f32, b: f32, tolerance: f32) {
+        assert!((a - b).abs() <= tolerance, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn reference_values() {
+        assert_close(srgb_encode(0.18), 0.461_356, 1e-6);
+        assert_close(srgb_decode(0.5), 0.214_041, 1e-6);
+        assert_close(srgb_encode(0.002), 0.025_84, 1e-6);
+        assert_close(TransferFunction::Gamma(2.2).decode(-0.5), -0.217_638, 1e-6);
+        // 100 cd/m² and the 10000 cd/m² peak
+        assert_close(TransferFunction::Pq.encode(0.01), 0.508_078, 1e-5);
+        assert_close(TransferFunction::Pq.encode(1.0), 1.0, 1e-6);
+        assert_close(TransferFunction::Pq.decode(0.0), 0.0, 1e-9);
+        // The two segments of HLG meet at 1/12, and 1 encodes to 1
+        assert_close(TransferFunction::Hlg.encode(1.0 / 12.0), 0.5, 1e-6);
+        assert_close(TransferFunction::Hlg.encode(1.0), 1.0, 1e-6);
+        // BT.1886 displays its black level at 0 and white at 1
+        assert_close(TransferFunction::Bt1886(0.01).decode(0.0), 0.01, 1e-6);
+        assert_close(TransferFunction::Bt1886(0.01).decode(1.0), 1.0, 1e-6);
+        assert_close(TransferFunction::Bt1886(0.0).decode(0.5), 0.5f32.powf(2.4), 1e-6);
+    }
+
+    #[test]
+    fn round_trip() {
+        for &function in &FUNCTIONS {
+            for i in 0..=20 {
+                let c = i as f32 / 20.0;
+                let encoded = function.encode(c);
+                let tolerance = if function == TransferFunction::Pq { 1e-4 } else { 1e-5 };
+                assert_close(function.decode(encoded), c, tolerance);
+                assert_close(function.encode(function.decode(c)), c, tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn constants_match_the_shader() {
+        glsl::assert_matches("BT1886_GAMMA", &[BT1886_GAMMA], 0.0);
+        glsl::assert_matches("PQ_M1", &[PQ_M1], 0.0);
+        glsl::assert_matches("PQ_M2", &[PQ_M2], 0.0);
+        glsl::assert_matches("PQ_C1", &[PQ_C1], 0.0);
+        glsl::assert_matches("PQ_C2", &[PQ_C2], 0.0);
+        glsl::assert_matches("PQ_C3", &[PQ_C3], 0.0);
+        glsl::assert_matches("HLG_A", &[HLG_A], 0.0);
+        glsl::assert_matches("HLG_C", &[HLG_C], 0.0);
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::color::{
+    AdaptationMethod, CvdModel, Illuminant, Light, Observer, RgbColorSpace, ToneMapping,
+    TransferFunction, YCbCrStandard,
+};
+use crate::diagram::Coordinates;
+use crate::process::Fit;
+use crate::scene::Space;
+
+/// Presentation described in a TOML file.
+///
+/// ```toml
+/// [[scene]]
+/// kind = "permutation"
+/// images = ["nature.png", "urban.png"]
+/// permutation = [0, 2, 1]
+/// keep_view = true
+/// fit = "cover"
+/// ```
+///
+/// Image paths are relative to the directory of the deck file.
+#[derive(Deserialize)]
+pub struct Deck {
+    #[serde(rename = "scene", default)]
+    pub scenes: Vec<Slide>,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+#[derive(Deserialize)]
+pub struct Slide {
+    #[serde(flatten)]
+    pub scene: SceneSpec,
+    /// Start from the view of the previous scene, or the last view if there are fewer
+    #[serde(default)]
+    pub keep_view: bool,
+    /// Placement of the scene in the window
+    #[serde(default)]
+    pub fit: Fit,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SceneSpec {
+    Plain {
+        image: ImageSpec,
+    },
+    Permutation {
+        images: Vec<ImageSpec>,
+        permutation: [usize; 3],
+        /// Color space of the channels that are permuted
+        #[serde(default)]
+        space: Space,
+    },
+    Channels {
+        images: [ImageSpec; 3],
+        #[serde(default)]
+        space: Space,
+    },
+    Combination {
+        steps: usize,
+        images: [ImageSpec; 2],
+    },
+    /// Steps through protan, deutan and tritan simulations after normal vision
+    Deficiencies {
+        image: ImageSpec,
+        #[serde(default)]
+        model: CvdModel,
+        /// From 0 for normal vision to 1 for dichromacy
+        #[serde(default = "full_severity")]
+        severity: f32,
+    },
+    /// Steps through exposures from `-stops` to `stops` tone mapped by `operator`.
+    /// Toggling clips the exposures instead.
+    Exposure {
+        image: ImageSpec,
+        #[serde(default)]
+        operator: ToneMapping,
+        /// Linear value mapped to 1, by default that of the operator
+        white: Option<f32>,
+        #[serde(default = "default_stops")]
+        stops: usize,
+    },
+    /// Marks and maps the colors pushed out of gamut by scaling the OKLCh chroma
+    Gamut {
+        image: ImageSpec,
+        #[serde(default = "default_chroma")]
+        chroma: f32,
+        /// Color of the pixels outside the gamut
+        #[serde(default = "default_warning")]
+        warning: [f32; 3],
+    },
+    /// Rotates the hue a full turn in `steps` views
+    Hue {
+        steps: usize,
+        image: ImageSpec,
+    },
+    /// Steps through the standard illuminants
+    Illuminants {
+        image: ImageSpec,
+        #[serde(default)]
+        method: AdaptationMethod,
+    },
+    /// Steps through pairs of reflectances that match under `matched` but not under
+    /// `other`. Toggling switches between the lights.
+    #[serde(rename = "illuminant_metamerism")]
+    IlluminantMetamerism {
+        #[serde(default)]
+        matched: Light,
+        #[serde(default = "incandescent")]
+        other: Light,
+    },
+    /// Steps through sets of reflectances that match under `light`, with their
+    /// curves. Toggling switches from the 2° to the 10° observer.
+    #[serde(rename = "observer_metamerism")]
+    ObserverMetamerism {
+        #[serde(default)]
+        light: Light,
+    },
+    Movement {
+        mask: ImageSpec,
+    },
+    /// Steps through sRGB, Display P3, Adobe RGB, Rec. 2020 and ProPhoto
+    /// encodings shown without color management
+    Spaces {
+        image: ImageSpec,
+    },
+    /// Steps through 4:4:4, 4:2:2 and 4:2:0 chroma subsampling
+    Subsampling {
+        image: ImageSpec,
+        #[serde(default)]
+        standard: YCbCrStandard,
+    },
+}
+
+/// Either a path to an image file or a table describing a generated image
+/// or a file with a transfer function other than sRGB
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ImageSpec {
+    File(PathBuf),
+    Generated(Generator),
+    /// `{ file = "ramp.png", transfer = { gamma = 2.2 } }`
+    Decoded {
+        file: PathBuf,
+        transfer: TransferFunction,
+    },
+}
+
+/// Generated images have the size of the window
+#[derive(Clone, Deserialize)]
+#[serde(tag = "generator", rename_all = "lowercase")]
+pub enum Generator {
+    Random,
+    Rgb,
+    /// A linear ramp above the ramp encoded with `transfer`
+    Gamma {
+        #[serde(default)]
+        transfer: TransferFunction,
+    },
+    Grayscale { value: f32 },
+    /// Chromaticity diagram with gamut triangles of `spaces` and optionally
+    /// the chromaticities of the pixels of the `scatter` image
+    Chromaticity {
+        #[serde(default)]
+        observer: Observer,
+        #[serde(default)]
+        coordinates: Coordinates,
+        #[serde(default)]
+        spaces: Vec<RgbColorSpace>,
+        scatter: Option<PathBuf>,
+    },
+    Monochrome { color: [f32; 3] },
+}
+
+fn full_severity() -> f32 {
+    1.0
+}
+
+fn default_chroma() -> f32 {
+    2.0
+}
+
+fn default_warning() -> [f32; 3] {
+    [1.0, 0.0, 1.0]
+}
+
+fn default_stops() -> usize {
+    4
+}
+
+fn incandescent() -> Light {
+    Light::Standard(Illuminant::A)
+}
+
+impl Deck {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).map_err(|err| Error::Io(path.to_owned(), err))?;
+        let mut deck: Self =
+            toml::from_str(&data).map_err(|err| Error::Deck(path.to_owned(), err))?;
+        deck.dir = path.parent().unwrap_or_else(|| Path::new("")).to_owned();
+        Ok(deck)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::Deck;
+
+    #[test]
+    fn bundled_decks_load() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("decks");
+        let mut n_decks = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                let deck = Deck::load(&path).unwrap_or_else(|err| panic!("{}", err));
+                assert!(!deck.scenes.is_empty(), "{} has no scenes", path.display());
+                n_decks += 1;
+            }
+        }
+        assert!(n_decks > 0);
+    }
+}
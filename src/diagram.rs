@@ -0,0 +1,342 @@
+//! Chromaticity diagrams and spectral swatches drawn on the CPU
+
+use cgmath::prelude::*;
+use cgmath::{Vector2, Vector3};
+
+use serde::Deserialize;
+
+use crate::color::{self, AdaptationMethod, Conversion, GamutMapping, Observer, RgbColorSpace, Spectrum, SRGB};
+
+/// Chromaticity coordinates of the diagram
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Coordinates {
+    /// CIE 1931 xy
+    #[default]
+    Xy,
+    /// CIE 1976 u'v', which is closer to perceptually uniform
+    Uv,
+}
+
+impl Coordinates {
+    fn chromaticity(self, xyz: Vector3<f32>) -> Vector2<f32> {
+        match self {
+            Coordinates::Xy => {
+                let sum = xyz.x + xyz.y + xyz.z;
+                Vector2::new(xyz.x / sum, xyz.y / sum)
+            }
+            Coordinates::Uv => {
+                let d = xyz.x + 15.0 * xyz.y + 3.0 * xyz.z;
+                Vector2::new(4.0 * xyz.x / d, 9.0 * xyz.y / d)
+            }
+        }
+    }
+
+    fn chromaticity_of_xy(self, [x, y]: [f32; 2]) -> Vector2<f32> {
+        self.chromaticity(color::xy_to_xyz([x, y]))
+    }
+
+    /// XYZ with Y = 1 of a chromaticity
+    fn xyz(self, c: Vector2<f32>) -> Vector3<f32> {
+        let [x, y] = match self {
+            Coordinates::Xy => [c.x, c.y],
+            Coordinates::Uv => {
+                let d = 6.0 * c.x - 16.0 * c.y + 12.0;
+                [9.0 * c.x / d, 4.0 * c.y / d]
+            }
+        };
+        color::xy_to_xyz([x, y])
+    }
+
+    /// Upper corner of the plotted area, which starts at the origin
+    fn extent(self) -> Vector2<f32> {
+        match self {
+            Coordinates::Xy => Vector2::new(0.8, 0.9),
+            Coordinates::Uv => Vector2::new(0.7, 0.65),
+        }
+    }
+}
+
+/// Colors of the gamut triangles, repeated when there are more spaces
+const TRIANGLE_COLORS: [[f32; 3]; 5] = [
+    [1.0, 1.0, 0.0],
+    [0.0, 1.0, 1.0],
+    [1.0, 0.0, 1.0],
+    [1.0, 0.5, 0.0],
+    [0.5, 1.0, 0.5],
+];
+
+/// Chromaticity diagram with the spectral locus, the purple line and the gamut
+/// triangles of `spaces`
+#[derive(Clone, Debug, Default)]
+pub struct Diagram {
+    pub observer: Observer,
+    pub coordinates: Coordinates,
+    pub spaces: Vec<RgbColorSpace>,
+}
+
+/// Linear rgb pixels with the first row at the bottom
+struct Canvas {
+    width: u32,
+    height: u32,
+    data: Vec<Vector3<f32>>,
+    /// Pixels per unit of the plotted coordinates along each axis
+    scale: Vector2<f32>,
+    /// Pixel position of the origin of the plotted coordinates
+    origin: Vector2<f32>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![Vector3::zero(); (width * height) as usize],
+            scale: Vector2::new(1.0, 1.0),
+            origin: Vector2::zero(),
+        }
+    }
+
+    fn to_pixel(&self, c: Vector2<f32>) -> Vector2<f32> {
+        self.origin + c.mul_element_wise(self.scale)
+    }
+
+    fn to_coordinates(&self, x: u32, y: u32) -> Vector2<f32> {
+        (Vector2::new(x as f32 + 0.5, y as f32 + 0.5) - self.origin).div_element_wise(self.scale)
+    }
+
+    /// Fill the pixels from `x0`, `y0` up to but excluding `x1`, `y1`
+    fn fill(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, color: Vector3<f32>) {
+        for y in y0..y1.min(self.height) {
+            for x in x0..x1.min(self.width) {
+                self.data[(y * self.width + x) as usize] = color;
+            }
+        }
+    }
+
+    /// Linear rgb values of the rows from bottom to top
+    fn into_rows(self) -> Vec<f32> {
+        self.data.iter().flat_map(|c| vec![c.x, c.y, c.z]).collect()
+    }
+
+    fn dot(&mut self, p: Vector2<f32>, radius: f32, color: Vector3<f32>) {
+        let r = radius.ceil() as i64;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let x = p.x as i64 + dx;
+                let y = p.y as i64 + dy;
+                if x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height {
+                    self.data[(y as u32 * self.width + x as u32) as usize] = color;
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, a: Vector2<f32>, b: Vector2<f32>, radius: f32, color: Vector3<f32>) {
+        let (a, b) = (self.to_pixel(a), self.to_pixel(b));
+        let steps = (2.0 * (b - a).magnitude()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            self.dot(a.lerp(b, i as f32 / steps as f32), radius, color);
+        }
+    }
+}
+
+/// Even-odd test of `p` against the closed `polygon`
+fn inside(polygon: &[Vector2<f32>], p: Vector2<f32>) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[j]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+impl Diagram {
+    /// Chromaticities of the spectral locus from 380 to 700 nm in 1 nm steps.
+    /// Longer wavelengths all have nearly the chromaticity of 700 nm.
+    fn locus(&self) -> Vec<Vector2<f32>> {
+        (380..=700)
+            .map(|nm| self.coordinates.chromaticity(self.observer.cmf(nm as f32)))
+            .collect()
+    }
+
+    /// Render to linear rgb rows from bottom to top, with the chromaticities of the
+    /// linear rgb `points` plotted on top
+    pub fn render(&self, width: u32, height: u32, points: &[Vector3<f32>]) -> Vec<f32> {
+        let extent = self.coordinates.extent();
+        let scale = (width as f32 / extent.x).min(height as f32 / extent.y);
+        let origin = (Vector2::new(width as f32, height as f32) - extent * scale) / 2.0;
+        let mut canvas = Canvas::new(width, height);
+        canvas.scale = Vector2::new(scale, scale);
+        canvas.origin = origin;
+        let radius = (width.min(height) as f32 / 500.0).max(0.5);
+        let locus = self.locus();
+
+        // Grid every 0.1
+        let grid = Vector3::new(0.02, 0.02, 0.02);
+        for i in 0..=(extent.x * 10.0).round() as usize {
+            let x = i as f32 / 10.0;
+            canvas.line(Vector2::new(x, 0.0), Vector2::new(x, extent.y), radius / 2.0, grid);
+        }
+        for i in 0..=(extent.y * 10.0).round() as usize {
+            let y = i as f32 / 10.0;
+            canvas.line(Vector2::new(0.0, y), Vector2::new(extent.x, y), radius / 2.0, grid);
+        }
+
+        // Chromaticities at their brightest displayable color
+        let to_rgb = color::xyz_to_rgb_matrix();
+        for y in 0..height {
+            for x in 0..width {
+                let c = canvas.to_coordinates(x, y);
+                if !inside(&locus, c) {
+                    continue;
+                }
+                let rgb = to_rgb * self.coordinates.xyz(c);
+                let rgb = rgb / rgb.x.max(rgb.y).max(rgb.z);
+                canvas.data[(y * width + x) as usize] =
+                    Conversion::GamutMap(GamutMapping::Clip).apply(rgb);
+            }
+        }
+
+        let to_xyz = color::rgb_to_xyz_matrix();
+        let white = Vector3::new(1.0, 1.0, 1.0);
+        for &rgb in points {
+            let xyz = to_xyz * rgb;
+            let sum = xyz.x + xyz.y + xyz.z;
+            if sum > 0.0 && sum.is_finite() {
+                let p = canvas.to_pixel(self.coordinates.chromaticity(xyz));
+                canvas.dot(p, 0.0, white);
+            }
+        }
+
+        // Spectral locus and the purple line that closes it
+        for (i, &a) in locus.iter().enumerate() {
+            let b = locus[(i + 1) % locus.len()];
+            canvas.line(a, b, radius, white);
+        }
+
+        for (space, color) in self.spaces.iter().zip(TRIANGLE_COLORS.iter().cycle()) {
+            let corners: Vec<_> = space.primaries.iter().map(|&p| self.coordinates.chromaticity_of_xy(p)).collect();
+            for i in 0..3 {
+                canvas.line(corners[i], corners[(i + 1) % 3], radius, Vector3::from(*color));
+            }
+            let white_point = canvas.to_pixel(self.coordinates.chromaticity_of_xy(space.white));
+            canvas.dot(white_point, 2.0 * radius, Vector3::from(*color));
+        }
+
+        canvas.into_rows()
+    }
+}
+
+/// Patches of surfaces lit by `illuminant` side by side, optionally with their
+/// reflectance curves below them
+#[derive(Clone, Debug)]
+pub struct Swatches {
+    pub observer: Observer,
+    pub illuminant: Spectrum,
+    /// Plot the reflectance from 0 to 1 over the visible spectrum below each patch
+    pub curves: bool,
+}
+
+impl Swatches {
+    /// Linear sRGB of a surface as seen by an observer adapted to the illuminant
+    pub fn color(&self, reflectance: &Spectrum) -> Vector3<f32> {
+        let xyz = reflectance.reflected(&self.illuminant, self.observer);
+        let white = self.illuminant.white(self.observer);
+        let adapt = color::adaptation_matrix(white, SRGB.white_xyz(), AdaptationMethod::Bradford);
+        SRGB.xyz_to_rgb_matrix() * adapt * xyz
+    }
+
+    /// Render to linear rgb rows from bottom to top with one column per reflectance
+    pub fn render(&self, width: u32, height: u32, reflectances: &[Spectrum]) -> Vec<f32> {
+        let mut canvas = Canvas::new(width, height);
+        let column = width / reflectances.len().max(1) as u32;
+        let gap = column.min(height) / 16;
+        let plot_height = if self.curves { height * 2 / 5 } else { 0 };
+        let radius = (width.min(height) as f32 / 500.0).max(0.5);
+        let grid = Vector3::new(0.02, 0.02, 0.02);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+
+        for (i, reflectance) in reflectances.iter().enumerate() {
+            let x0 = i as u32 * column + gap;
+            let x1 = (i as u32 + 1) * column - gap;
+            let rgb = Conversion::GamutMap(GamutMapping::Clip).apply(self.color(reflectance));
+            canvas.fill(x0, plot_height + gap, x1, height - gap, rgb);
+            if !self.curves {
+                continue;
+            }
+
+            // Wavelengths relative to the first sample against reflectance
+            let last = reflectance.values.len() - 1;
+            let range = Spectrum::wavelength(last) - Spectrum::wavelength(0);
+            canvas.origin = Vector2::new(x0 as f32, gap as f32);
+            canvas.scale = Vector2::new((x1 - x0) as f32 / range, plot_height.saturating_sub(2 * gap) as f32);
+            for nm in (0..=range as usize).step_by(100) {
+                let x = nm as f32;
+                canvas.line(Vector2::new(x, 0.0), Vector2::new(x, 1.0), radius / 2.0, grid);
+            }
+            for &y in &[0.0, 0.5, 1.0] {
+                canvas.line(Vector2::new(0.0, y), Vector2::new(range, y), radius / 2.0, grid);
+            }
+            for (j, pair) in reflectance.values.windows(2).enumerate() {
+                let a = Vector2::new(Spectrum::wavelength(j) - Spectrum::wavelength(0), pair[0]);
+                let b = Vector2::new(Spectrum::wavelength(j + 1) - Spectrum::wavelength(0), pair[1]);
+                canvas.line(a, b, radius, white);
+            }
+        }
+
+        canvas.into_rows()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::prelude::*;
+    use cgmath::{Vector2, Vector3};
+
+    use super::*;
+
+    fn assert_close(a: Vector2<f32>, b: Vector2<f32>, tolerance: f32) {
+        assert!((a - b).magnitude() < tolerance, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn chromaticity_coordinates() {
+        let d65 = SRGB.white_xyz();
+        assert_close(Coordinates::Xy.chromaticity(d65), Vector2::new(0.3127, 0.3290), 1e-6);
+        assert_close(Coordinates::Uv.chromaticity(d65), Vector2::new(0.1978, 0.4683), 1e-4);
+        // Green at 520 nm, close to the top of the locus
+        let green = Observer::Cie1931.cmf(520.0);
+        assert_close(Coordinates::Xy.chromaticity(green), Vector2::new(0.0743, 0.8338), 1e-4);
+        for &coordinates in &[Coordinates::Xy, Coordinates::Uv] {
+            for &xyz in &[d65, green, Vector3::new(0.2, 0.1, 0.7)] {
+                let c = coordinates.chromaticity(xyz);
+                assert_close(coordinates.chromaticity(coordinates.xyz(c)), c, 1e-6);
+                assert!((coordinates.xyz(c).y - 1.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn render_fills_the_locus() {
+        let (width, height) = (160, 180);
+        let diagram = Diagram::default();
+        let data = diagram.render(width, height, &[]);
+        assert_eq!(data.len(), 3 * width as usize * height as usize);
+        // The xy extent fits the image exactly, one pixel per 0.005
+        let pixel = |x: f32, y: f32| {
+            let i = 3 * ((y / 0.005) as usize * width as usize + (x / 0.005) as usize);
+            Vector3::new(data[i], data[i + 1], data[i + 2])
+        };
+        let white = pixel(0.3127, 0.3290);
+        assert!((white - Vector3::new(1.0, 1.0, 1.0)).magnitude() < 0.05, "{:?}", white);
+        let red = pixel(0.62, 0.33);
+        assert!(red.x > 0.9 && red.y < 0.2 && red.z < 0.2, "{:?}", red);
+        assert_eq!(pixel(0.72, 0.82), Vector3::zero());
+        assert_eq!(pixel(0.04, 0.03), Vector3::zero());
+    }
+}
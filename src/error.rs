@@ -0,0 +1,69 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use glium::texture::TextureCreationError;
+
+use image::ImageError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed
+    Io(PathBuf, io::Error),
+    /// Neither the file contents nor the extension name a supported format
+    UnsupportedFormat(PathBuf),
+    /// The file is not a valid image of its format
+    Decode(PathBuf, ImageError),
+    /// The embedded ICC profile is invalid or not a matrix and curves profile
+    Profile(PathBuf, String),
+    /// The backend could not create a texture for the image
+    Upload(String),
+    /// The number of pixels does not match a width and height, or the size is empty
+    SizeMismatch(usize, u32, u32),
+    /// The presentation file is not valid
+    Deck(PathBuf, toml::de::Error),
+    /// The parameters of a scene are not valid
+    InvalidScene(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            Error::UnsupportedFormat(path) => {
+                write!(f, "{}: Unsupported image format", path.display())
+            }
+            Error::Decode(path, err) => write!(f, "{}: Failed to decode: {}", path.display(), err),
+            Error::Profile(path, msg) => write!(f, "{}: Unsupported ICC profile: {}", path.display(), msg),
+            Error::Upload(msg) => write!(f, "Failed to upload texture: {}", msg),
+            Error::SizeMismatch(pixels, width, height) => {
+                write!(f, "{} pixels given for a {}x{} image", pixels, width, height)
+            }
+            Error::Deck(path, err) => write!(f, "{}: Invalid presentation: {}", path.display(), err),
+            Error::InvalidScene(msg) => write!(f, "Invalid scene: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, err) => Some(err),
+            Error::Decode(_, err) => Some(err),
+            Error::Deck(_, err) => Some(err),
+            Error::UnsupportedFormat(_)
+            | Error::Profile(..)
+            | Error::Upload(_)
+            | Error::SizeMismatch(..)
+            | Error::InvalidScene(_) => None,
+        }
+    }
+}
+
+impl From<TextureCreationError> for Error {
+    fn from(err: TextureCreationError) -> Self {
+        Error::Upload(err.to_string())
+    }
+}
@@ -1,107 +1,257 @@
 use std::rc::Rc;
-use std::fs::File;
-use std::io::BufReader;
+use std::fs;
 use std::path::Path;
 
 use cgmath::prelude::*;
-use cgmath::{Matrix4, Vector4, Vector2};
+use cgmath::{Matrix3, Matrix4, Vector4, Vector3, Vector2};
 
-use glium::texture::{RawImage2d, SrgbTexture2d, Texture2d};
+use image::hdr::{HDRDecoder, HDRMetadata};
+use image::{ImageFormat, RgbImage};
 
-use image::{GenericImage, ImageFormat, RgbaImage};
+use crate::backend::Backend;
+use crate::color::{
+    self, AdaptationMethod, Conversion, CvdModel, Deficiency, GamutMapping, IccProfile, Range, RgbColorSpace,
+    Spectrum, Subsampling, ToneMapping, TransferFunction, YCbCrStandard,
+};
+use crate::diagram::{Diagram, Swatches};
+use crate::error::{Error, Result};
+use crate::input;
+use crate::output::{self, SaveOptions};
+use crate::process::{Fit, Processor};
 
-use crate::process::Processor;
+fn format_from_extension(path: &Path) -> Option<ImageFormat> {
+    let format = match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "png" => ImageFormat::PNG,
+        "jpg" | "jpeg" => ImageFormat::JPEG,
+        "gif" => ImageFormat::GIF,
+        "webp" => ImageFormat::WEBP,
+        "pnm" => ImageFormat::PNM,
+        "tiff" => ImageFormat::TIFF,
+        "tga" => ImageFormat::TGA,
+        "bmp" => ImageFormat::BMP,
+        "ico" => ImageFormat::ICO,
+        "hdr" => ImageFormat::HDR,
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// How `Image::load` decodes the stored values
+#[derive(Clone, Copy, PartialEq)]
+enum Decoding {
+    /// With the embedded profile, or as sRGB if it has none or it is not supported
+    Profile,
+    /// With the embedded profile, failing if it is not supported
+    RequireProfile,
+    /// With a transfer function, ignoring any embedded profile
+    Transfer(TransferFunction),
+}
 
-#[derive(Clone)]
-pub struct Image<'a> {
-    texture: Rc<Texture2d>,
-    pub processor: &'a Processor<'a>,
+pub struct Image<'a, B: Backend + 'a = Processor> {
+    texture: Rc<B::Texture>,
+    pub backend: &'a B,
+}
+
+impl<B: Backend> Clone for Image<'_, B> {
+    fn clone(&self) -> Self {
+        Self {
+            texture: self.texture.clone(),
+            backend: self.backend,
+        }
+    }
 }
 
 #[allow(dead_code)]
-impl<'a> Image<'a> {
-    pub fn new(processor: &'a Processor<'a>, path: &Path) -> Self {
-        let image_format = match path.extension().unwrap().to_str().unwrap() {
-            "png" => ImageFormat::PNG,
-            "jpg" | "jpeg" => ImageFormat::JPEG,
-            "gif" => ImageFormat::GIF,
-            "webp" => ImageFormat::WEBP,
-            "pnm" => ImageFormat::PNM,
-            "tiff" => ImageFormat::TIFF,
-            "tga" => ImageFormat::TGA,
-            "bmp" => ImageFormat::BMP,
-            "ico" => ImageFormat::ICO,
-            "hdr" => ImageFormat::HDR,
-            ext => {
-                panic!("Unknown image extension {}", ext);
+impl<'a, B: Backend> Image<'a, B> {
+    /// Load an image into the linear working space. Embedded ICC profiles with
+    /// matrices and curves are applied, other images are assumed to be sRGB.
+    /// Images with other profiles are loaded as sRGB with a warning. The format
+    /// is detected from the file contents and the extension is only used as a
+    /// fallback.
+    pub fn new(backend: &'a B, path: &Path) -> Result<Self> {
+        Self::load(backend, path, Decoding::Profile)
+    }
+
+    /// Load an image like `new`, but fail with `Error::Profile` instead of
+    /// assuming sRGB when the embedded profile is not supported
+    pub fn with_embedded_profile(backend: &'a B, path: &Path) -> Result<Self> {
+        Self::load(backend, path, Decoding::RequireProfile)
+    }
+
+    /// Load an image whose values are encoded with `transfer` instead of sRGB,
+    /// ignoring any embedded profile. Radiance files are already linear and are
+    /// loaded as they are.
+    pub fn with_transfer(backend: &'a B, path: &Path, transfer: TransferFunction) -> Result<Self> {
+        Self::load(backend, path, Decoding::Transfer(transfer))
+    }
+
+    fn load(backend: &'a B, path: &Path, decoding: Decoding) -> Result<Self> {
+        let data = fs::read(path).map_err(|err| Error::Io(path.to_owned(), err))?;
+        let image_format = image::guess_format(&data)
+            .ok()
+            .or_else(|| format_from_extension(path))
+            .ok_or_else(|| Error::UnsupportedFormat(path.to_owned()))?;
+        if image_format == ImageFormat::HDR {
+            return Self::read_hdr(backend, path, &data);
+        }
+        let image = image::load_from_memory_with_format(&data, image_format)
+            .map_err(|err| Error::Decode(path.to_owned(), err))?
+            .to_rgb();
+        if let Decoding::Transfer(transfer) = decoding {
+            let (width, height) = image.dimensions();
+            let data = image
+                .chunks(3 * width as usize)
+                .rev()
+                .flatten()
+                .map(|&c| f32::from(c) / 255.0)
+                .collect();
+            let encoded = Self {
+                texture: Rc::new(backend.upload(width, height, data)?), backend
+            };
+            return Ok(encoded.decode(transfer));
+        }
+        let profile = input::icc_profile(&data, image_format).map(|profile| IccProfile::parse(&profile));
+        let profile = match profile {
+            Some(Ok(profile)) => Some(profile),
+            Some(Err(err)) if decoding == Decoding::RequireProfile => {
+                return Err(Error::Profile(path.to_owned(), err))
+            }
+            Some(Err(err)) => {
+                eprintln!("{}: Assuming sRGB, unsupported ICC profile: {}", path.display(), err);
+                None
             }
+            None => None,
         };
-        let reader = BufReader::new(File::open(path).unwrap());
-        let image = image::load(reader, image_format).unwrap();
-        let image_dim = image.dimensions();
-        let tex_image = RawImage2d::from_raw_rgb_reversed(&image.to_rgb().into_raw(), image_dim);
-        let srgb = SrgbTexture2d::new(processor.display, tex_image).unwrap();
-        let texture = processor.srgb_to_linear(&srgb);
-        Self {
-            texture: Rc::new(texture), processor
+        match profile {
+            Some(profile) => Self::apply_profile(backend, &image, &profile),
+            None => Ok(Self {
+                texture: Rc::new(backend.upload_srgb(&image)?), backend
+            }),
         }
     }
 
-    pub fn random(processor: &'a Processor<'a>) -> Self {
-        let w = processor.width;
-        let h = processor.height;
-        let len = (3 * w * h) as usize;
+    /// Apply the curves of `profile` through a table of the 256 values and its
+    /// matrix on the backend
+    fn apply_profile(backend: &'a B, image: &RgbImage, profile: &IccProfile) -> Result<Self> {
+        let table: Vec<_> = (0..=255u8)
+            .map(|i| profile.linear(Vector3::from_value(f32::from(i) / 255.0)))
+            .collect();
+        let (width, height) = image.dimensions();
+        let data = image
+            .chunks(3 * width as usize)
+            .rev()
+            .flat_map(|row| row.iter().enumerate().map(|(i, &c)| table[usize::from(c)][i % 3]))
+            .collect();
+        let device = Self {
+            texture: Rc::new(backend.upload(width, height, data)?), backend
+        };
+        Ok(device.transform(Matrix4::from(profile.working_space_matrix())))
+    }
+
+    /// Radiance files are decoded to floats so that values above 1 are kept
+    fn read_hdr(backend: &'a B, path: &Path, data: &[u8]) -> Result<Self> {
+        let decoder = HDRDecoder::new(data).map_err(|err| Error::Decode(path.to_owned(), err))?;
+        let HDRMetadata { width, height, .. } = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()
+            .map_err(|err| Error::Decode(path.to_owned(), err))?;
+        let data = pixels
+            .chunks(width as usize)
+            .rev()
+            .flatten()
+            .flat_map(|pixel| pixel.data.to_vec())
+            .collect();
+        Ok(Self {
+            texture: Rc::new(backend.upload(width, height, data)?), backend
+        })
+    }
+
+    /// Image from linear rgba `pixels` with rows from top to bottom, the inverse
+    /// of `to_rgba32f`
+    pub fn from_rgba32f(backend: &'a B, width: u32, height: u32, pixels: &[[f32; 4]]) -> Result<Self> {
+        let expected = (width as usize).checked_mul(height as usize);
+        if width == 0 || height == 0 || expected != Some(pixels.len()) {
+            return Err(Error::SizeMismatch(pixels.len(), width, height));
+        }
+        let data: Vec<_> = pixels.chunks(width as usize).rev().flatten().cloned().collect();
+        Ok(Self {
+            texture: Rc::new(backend.upload_rgba(width, height, &data)?), backend
+        })
+    }
+
+    /// Chromaticity diagram with the chromaticities of the pixels of `scatter` plotted on it
+    pub fn chromaticity_diagram(
+        backend: &'a B,
+        w: u32,
+        h: u32,
+        diagram: &Diagram,
+        scatter: Option<&Self>,
+    ) -> Result<Self> {
+        let points: Vec<_> = match scatter {
+            Some(image) => image
+                .backend
+                .read(&image.texture)
+                .iter()
+                .map(|p| Vector3::new(p[0], p[1], p[2]))
+                .collect(),
+            None => Vec::new(),
+        };
+        Ok(Self {
+            texture: Rc::new(backend.upload(w, h, diagram.render(w, h, &points))?), backend
+        })
+    }
+
+    /// Patches of the surfaces with the `reflectances` lit by the light of `swatches`
+    pub fn swatches(backend: &'a B, w: u32, h: u32, swatches: &Swatches, reflectances: &[Spectrum]) -> Result<Self> {
+        Ok(Self {
+            texture: Rc::new(backend.upload(w, h, swatches.render(w, h, reflectances))?), backend
+        })
+    }
+
+    pub fn random(backend: &'a B, w: u32, h: u32) -> Result<Self> {
+        let len = 3 * w as usize * h as usize;
         let mut data = Vec::with_capacity(len);
         for _ in 0..len {
             data.push(rand::random::<f32>());
         }
-        let tex_image = RawImage2d::from_raw_rgb(data, (w, h));
-        let texture = Texture2d::new(processor.display, tex_image).unwrap();
-        Self {
-            texture: Rc::new(texture), processor
-        }
+        Ok(Self {
+            texture: Rc::new(backend.upload(w, h, data)?), backend
+        })
     }
 
-    pub fn grayscale(processor: &'a Processor<'a>, val: f32) -> Self {
-        Self::monochrome(processor, val, val, val)
+    pub fn grayscale(backend: &'a B, val: f32) -> Result<Self> {
+        Self::monochrome(backend, val, val, val)
     }
 
-    pub fn monochrome(processor: &'a Processor<'a>, r: f32, g: f32, b: f32) -> Self {
+    pub fn monochrome(backend: &'a B, r: f32, g: f32, b: f32) -> Result<Self> {
         let data = vec!(r, g, b);
-        let tex_image = RawImage2d::from_raw_rgb(data, (1, 1));
-        let texture = Texture2d::new(processor.display, tex_image).unwrap();
-        Self {
-            texture: Rc::new(texture), processor
-        }
+        Ok(Self {
+            texture: Rc::new(backend.upload(1, 1, data)?), backend
+        })
     }
 
-    pub fn gamma(processor: &'a Processor<'a>) -> Self {
-        let w = processor.width;
-        let h = processor.height;
-        let len = (3 * w * h) as usize;
+    /// Linear ramp with the ramp encoded by `transfer` below it
+    pub fn gamma(backend: &'a B, w: u32, h: u32, transfer: TransferFunction) -> Result<Self> {
+        let len = 3 * w as usize * h as usize;
         let mut data = Vec::with_capacity(len);
         for y in 0..h {
             for x in 0..w {
                 let mut c = x as f32 / w as f32;
                 if y > h / 2 {
-                    c = c.powf(1.0 / 2.2)
+                    c = transfer.encode(c)
                 }
                 for _ in 0..3 {
                     data.push(c);
                 }
             }
         }
-        let tex_image = RawImage2d::from_raw_rgb(data, (w, h));
-        let texture = Texture2d::new(processor.display, tex_image).unwrap();
-        Self {
-            texture: Rc::new(texture), processor
-        }
+        Ok(Self {
+            texture: Rc::new(backend.upload(w, h, data)?), backend
+        })
     }
 
-    pub fn rgb(processor: &'a Processor<'a>) -> Self {
-        let w = processor.width;
-        let h = processor.height;
-        let len = (3 * w * h) as usize;
+    pub fn rgb(backend: &'a B, w: u32, h: u32) -> Result<Self> {
+        let len = 3 * w as usize * h as usize;
         let mut data = Vec::with_capacity(len);
         for _ in 0..h {
             for x in 0..w {
@@ -120,11 +270,32 @@ impl<'a> Image<'a> {
                 }
             }
         }
-        let tex_image = RawImage2d::from_raw_rgb(data, (w, h));
-        let texture = Texture2d::new(processor.display, tex_image).unwrap();
-        Self {
-            texture: Rc::new(texture), processor
+        Ok(Self {
+            texture: Rc::new(backend.upload(w, h, data)?), backend
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.dimensions().0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.dimensions().1
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.backend.dimensions(&self.texture)
+    }
+
+    /// Linear rgba of the pixels with rows from top to bottom, `width` pixels
+    /// per row
+    pub fn to_rgba32f(&self) -> Vec<[f32; 4]> {
+        let width = self.width() as usize;
+        if width == 0 {
+            return Vec::new();
         }
+        let pixels = self.backend.read(&self.texture);
+        pixels.chunks(width).rev().flatten().cloned().collect()
     }
 
     pub fn r(&self) -> Self {
@@ -158,15 +329,33 @@ impl<'a> Image<'a> {
         let diag = Vector4::new(x, y, z, 1.0);
         let mat = Matrix4::from_diagonal(diag);
         Self {
-            texture: Rc::new(self.processor.transform(&self.texture, mat)),
-            processor: self.processor,
+            texture: Rc::new(self.backend.transform(&self.texture, mat)),
+            backend: self.backend,
+        }
+    }
+
+    /// Add a constant to each channel
+    pub fn offset(&self, x: f32, y: f32, z: f32) -> Self {
+        let mut mat = Matrix4::identity();
+        mat.w = Vector4::new(x, y, z, 1.0);
+        Self {
+            texture: Rc::new(self.backend.transform(&self.texture, mat)),
+            backend: self.backend,
+        }
+    }
+
+    /// Resample to `width` x `height` with bilinear filtering
+    pub fn resize(&self, width: u32, height: u32) -> Self {
+        Self {
+            texture: Rc::new(self.backend.resize(&self.texture, (width, height))),
+            backend: self.backend,
         }
     }
 
     pub fn shift(&self, x: f32, y: f32) -> Self {
         Self {
-            texture: Rc::new(self.processor.shift(&self.texture, Vector2::new(x, y))),
-            processor: self.processor,
+            texture: Rc::new(self.backend.shift(&self.texture, Vector2::new(x, y))),
+            backend: self.backend,
         }
     }
 
@@ -182,78 +371,408 @@ impl<'a> Image<'a> {
         mat.w.w = 1.0;
         let mat = mat.transpose();
         Self {
-            texture: Rc::new(self.processor.transform(&self.texture, mat)),
-            processor: self.processor,
+            texture: Rc::new(self.backend.transform(&self.texture, mat)),
+            backend: self.backend,
         }
     }
 
-    #[allow(clippy::unreadable_literal)]
     pub fn rgb_to_xyz(&self) -> Self {
-        let to_xyz = Matrix4::new(
-            0.412453, 0.35758, 0.180423, 0.0,
-            0.212671, 0.71516, 0.072169, 0.0,
-            0.019334, 0.119193, 0.950227, 0.0,
-            0.0, 0.0, 0.0, 1.0
-        ).transpose();
+        let to_xyz = Matrix4::from(color::rgb_to_xyz_matrix());
         Self {
-            texture: Rc::new(self.processor.transform(&self.texture, to_xyz)),
-            processor: self.processor,
+            texture: Rc::new(self.backend.transform(&self.texture, to_xyz)),
+            backend: self.backend,
         }
     }
 
-    #[allow(clippy::unreadable_literal)]
     pub fn xyz_to_rgb(&self) -> Self {
-        let to_rgb = Matrix4::new(
-            3.240479, -1.53715, -0.498535, 0.0,
-            -0.969256, 1.875991, 0.041556, 0.0,
-            0.055648, -0.204043, 1.057311, 0.0,
-            0.0, 0.0, 0.0, 1.0
-        ).transpose();
+        let to_rgb = Matrix4::from(color::xyz_to_rgb_matrix());
+        Self {
+            texture: Rc::new(self.backend.transform(&self.texture, to_rgb)),
+            backend: self.backend,
+        }
+    }
+
+    /// Apply a non-linear per pixel color conversion
+    pub fn convert(&self, conversion: Conversion) -> Self {
+        Self {
+            texture: Rc::new(self.backend.convert(&self.texture, conversion)),
+            backend: self.backend,
+        }
+    }
+
+    /// Convert to CIE L*a*b* relative to the reference `white` in XYZ, e.g. `color::D65`
+    pub fn rgb_to_lab(&self, white: Vector3<f32>) -> Self {
+        self.rgb_to_xyz().convert(Conversion::XyzToLab { white })
+    }
+
+    pub fn lab_to_rgb(&self, white: Vector3<f32>) -> Self {
+        self.convert(Conversion::LabToXyz { white }).xyz_to_rgb()
+    }
+
+    /// Convert to CIE L*C*h with hue in degrees
+    pub fn rgb_to_lch(&self, white: Vector3<f32>) -> Self {
+        self.rgb_to_lab(white).convert(Conversion::LabToLch)
+    }
+
+    pub fn lch_to_rgb(&self, white: Vector3<f32>) -> Self {
+        self.convert(Conversion::LchToLab).lab_to_rgb(white)
+    }
+
+    pub fn rgb_to_oklab(&self) -> Self {
+        self.convert(Conversion::RgbToOklab)
+    }
+
+    pub fn oklab_to_rgb(&self) -> Self {
+        self.convert(Conversion::OklabToRgb)
+    }
+
+    /// Convert to OKLCh with hue in degrees
+    pub fn rgb_to_oklch(&self) -> Self {
+        self.rgb_to_oklab().convert(Conversion::LabToLch)
+    }
+
+    pub fn oklch_to_rgb(&self) -> Self {
+        self.convert(Conversion::LchToLab).oklab_to_rgb()
+    }
+
+    /// Convert to hue in degrees, saturation and value
+    pub fn rgb_to_hsv(&self) -> Self {
+        self.convert(Conversion::RgbToHsv)
+    }
+
+    pub fn hsv_to_rgb(&self) -> Self {
+        self.convert(Conversion::HsvToRgb)
+    }
+
+    /// Convert to hue in degrees, saturation and lightness
+    pub fn rgb_to_hsl(&self) -> Self {
+        self.convert(Conversion::RgbToHsl)
+    }
+
+    pub fn hsl_to_rgb(&self) -> Self {
+        self.convert(Conversion::HslToRgb)
+    }
+
+    /// Convert to hue in degrees, saturation and intensity
+    pub fn rgb_to_hsi(&self) -> Self {
+        self.convert(Conversion::RgbToHsi)
+    }
+
+    pub fn hsi_to_rgb(&self) -> Self {
+        self.convert(Conversion::HsiToRgb)
+    }
+
+    /// Rotate the HSV hue, keeping saturation and value
+    pub fn hue_rotate(&self, degrees: f32) -> Self {
+        self.rgb_to_hsv().offset(degrees, 0.0, 0.0).hsv_to_rgb()
+    }
+
+    /// Scale the HSV saturation. Saturation is limited to 1.
+    pub fn saturate(&self, factor: f32) -> Self {
+        self.rgb_to_hsv().scale(1.0, factor, 1.0).hsv_to_rgb()
+    }
+
+    /// Replace each pixel with its luminance
+    pub fn desaturate(&self) -> Self {
+        let weights = color::rgb_to_xyz_matrix().row(1);
+        let to_luminance = Matrix3::from_cols(
+            Vector3::from_value(weights.x),
+            Vector3::from_value(weights.y),
+            Vector3::from_value(weights.z),
+        );
+        self.transform(Matrix4::from(to_luminance))
+    }
+
+    fn transform(&self, transform: Matrix4<f32>) -> Self {
         Self {
-            texture: Rc::new(self.processor.transform(&self.texture, to_rgb)),
-            processor: self.processor,
+            texture: Rc::new(self.backend.transform(&self.texture, transform)),
+            backend: self.backend,
         }
     }
 
-    pub fn visualize(&self) {
-        self.processor.visualize(&self.texture);
+    /// Apply the transfer function from linear to encoded values
+    pub fn encode(&self, transfer: TransferFunction) -> Self {
+        self.convert(Conversion::Encode(transfer))
     }
 
-    pub fn save(&self, path: &Path) {
-        let srgb = self.processor.linear_to_srgb(&self.texture);
-        let pb = srgb.read_to_pixel_buffer();
-        let raw_image: RawImage2d<u8> = pb.read_as_texture_2d().unwrap();
-        let image = RgbaImage::from_vec(self.processor.width, self.processor.height,
-                                        raw_image.data.to_vec()).unwrap();
-        let image = image::imageops::flip_vertical(&image);
-        image.save(path).unwrap();
+    pub fn decode(&self, transfer: TransferFunction) -> Self {
+        self.convert(Conversion::Decode(transfer))
+    }
+
+    pub fn srgb_encode(&self) -> Self {
+        self.encode(TransferFunction::Srgb)
+    }
+
+    pub fn srgb_decode(&self) -> Self {
+        self.decode(TransferFunction::Srgb)
+    }
+
+    /// Convert linear rgb of the space `from` to linear rgb of `to`, adapting
+    /// between white points with Bradford
+    pub fn convert_rgb_space(&self, from: &RgbColorSpace, to: &RgbColorSpace) -> Self {
+        let mat = from.conversion_matrix(to, AdaptationMethod::Bradford);
+        self.transform(Matrix4::from(mat))
+    }
+
+    /// Convert to Y'CbCr of the sRGB encoded image like a video codec
+    pub fn rgb_to_ycbcr(&self, standard: YCbCrStandard, range: Range) -> Self {
+        self.srgb_encode().transform(color::ycbcr_matrix(standard, range))
+    }
+
+    pub fn ycbcr_to_rgb(&self, standard: YCbCrStandard, range: Range) -> Self {
+        let to_rgb = color::ycbcr_matrix(standard, range).invert().unwrap();
+        self.transform(to_rgb).srgb_decode()
+    }
+
+    /// Convert to YCoCg of the sRGB encoded image
+    pub fn rgb_to_ycocg(&self) -> Self {
+        self.srgb_encode().transform(color::ycocg_matrix())
+    }
+
+    pub fn ycocg_to_rgb(&self) -> Self {
+        self.transform(color::ycocg_matrix().invert().unwrap()).srgb_decode()
+    }
+
+    /// Store the chroma at reduced resolution and upsample it again like a video decoder
+    pub fn subsample_chroma(&self, subsampling: Subsampling, standard: YCbCrStandard) -> Self {
+        let ycbcr = self.rgb_to_ycbcr(standard, Range::Full);
+        let (width, height) = self.dimensions();
+        let (chroma_width, chroma_height) = subsampling.chroma_size((width, height));
+        let chroma = ycbcr.resize(chroma_width, chroma_height).resize(width, height);
+        Self::channels(&ycbcr, &chroma, &chroma).ycbcr_to_rgb(standard, Range::Full)
+    }
+
+    /// Map the colors of an image seen under the white point `from_white` to the
+    /// colors that look the same under `to_white`. Both are in XYZ, e.g. `Illuminant::white`.
+    pub fn chromatic_adapt(
+        &self,
+        from_white: Vector3<f32>,
+        to_white: Vector3<f32>,
+        method: AdaptationMethod,
+    ) -> Self {
+        let to_xyz = color::rgb_to_xyz_matrix();
+        let adapt = color::adaptation_matrix(from_white, to_white, method);
+        self.transform(Matrix4::from(to_xyz.invert().unwrap() * adapt * to_xyz))
+    }
+
+    /// Convert to the responses of the long, medium and short wavelength cones
+    pub fn rgb_to_lms(&self) -> Self {
+        self.transform(Matrix4::from(color::rgb_to_lms_matrix()))
+    }
+
+    pub fn lms_to_rgb(&self) -> Self {
+        self.transform(Matrix4::from(color::lms_to_rgb_matrix()))
+    }
+
+    /// Simulate how a person with `deficiency` sees the image. `severity` goes from
+    /// 0 for normal vision to 1 for dichromacy.
+    pub fn simulate_cvd(&self, deficiency: Deficiency, model: CvdModel, severity: f32) -> Self {
+        match model {
+            CvdModel::Brettel => self.convert(Conversion::Brettel { deficiency, severity }),
+            CvdModel::Vienot => {
+                self.transform(Matrix4::from(color::vienot_matrix(deficiency, severity)))
+            }
+            CvdModel::Machado => {
+                self.transform(Matrix4::from(color::machado_matrix(deficiency, severity)))
+            }
+        }
+    }
+
+    /// Recolor the image so that a person with `deficiency` can tell apart colors
+    /// they would otherwise confuse. `strength` 0 leaves the image unchanged.
+    pub fn daltonize(&self, deficiency: Deficiency, strength: f32) -> Self {
+        self.transform(Matrix4::from(color::daltonize_matrix(deficiency, strength)))
+    }
+
+    /// Show colors outside the displayable [0, 1] rgb cube in `color`
+    pub fn gamut_warning(&self, color: Vector3<f32>) -> Self {
+        self.convert(Conversion::GamutWarning { color })
+    }
+
+    /// Bring colors into the displayable [0, 1] rgb cube
+    pub fn gamut_map(&self, mapping: GamutMapping) -> Self {
+        self.convert(Conversion::GamutMap(mapping))
+    }
+
+    /// Scale linear values by 2 to the power of `stops`
+    pub fn expose(&self, stops: f32) -> Self {
+        self.uscale(stops.exp2())
+    }
+
+    /// Expose by `stops` and compress the result into [0, 1] with `white` as the
+    /// white point. `ReinhardLocal` ignores the white point.
+    pub fn tone_map(&self, mapping: ToneMapping, stops: f32, white: f32) -> Self {
+        let exposed = self.expose(stops);
+        if mapping != ToneMapping::ReinhardLocal {
+            return exposed.convert(Conversion::ToneMap { mapping, white });
+        }
+        // 1 / (1 + L) of the surrounding luminance L is 1 - L / (1 + L)
+        let mut invert = Matrix4::from_scale(-1.0);
+        invert.w = Vector4::new(1.0, 1.0, 1.0, 1.0);
+        let compression = exposed
+            .surround_luminance()
+            .convert(Conversion::ToneMap { mapping, white })
+            .transform(invert);
+        Image::mul(&exposed, &compression)
+    }
+
+    /// Luminance averaged over blocks of 1/32 of the size by halving five times,
+    /// then interpolated back to the full size
+    fn surround_luminance(&self) -> Self {
+        let (width, height) = self.dimensions();
+        let (mut w, mut h) = (width, height);
+        let mut blurred = self.desaturate();
+        for _ in 0..5 {
+            w = (w / 2).max(1);
+            h = (h / 2).max(1);
+            blurred = blurred.resize(w, h);
+        }
+        blurred.resize(width, height)
+    }
+
+    /// Save in the format given by the extension of `path`, as linear values in
+    /// float formats and as sRGB otherwise
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.save_with(path, &SaveOptions::default())
+    }
+
+    /// Save with the values encoded by `transfer` instead of the default of the format
+    pub fn save_with_transfer(&self, path: &Path, transfer: TransferFunction) -> Result<()> {
+        let options = SaveOptions {
+            transfer: Some(transfer),
+            ..SaveOptions::default()
+        };
+        self.save_with(path, &options)
+    }
+
+    pub fn save_with(&self, path: &Path, options: &SaveOptions) -> Result<()> {
+        let format = options.format(path);
+        let transfer = options.transfer(format);
+        let encoded = match transfer {
+            TransferFunction::Linear => self.clone(),
+            transfer => self.encode(transfer),
+        };
+        let (width, height) = self.dimensions();
+        let pixels = self.backend.read(&encoded.texture);
+        let profile = options.profile(transfer);
+        output::write(path, format, width, height, &pixels, profile.as_deref())
+            .map_err(|err| Error::Io(path.to_owned(), err))
     }
 
     pub fn diff(i1: &Self, i2: &Self, use_abs: bool) -> Self {
         Self {
-            texture: Rc::new(i1.processor.diff(&i1.texture, &i2.texture, use_abs)),
-            processor: i1.processor,
+            texture: Rc::new(i1.backend.diff(&i1.texture, &i2.texture, use_abs)),
+            backend: i1.backend,
         }
     }
 
     pub fn add(i1: &Self, i2: &Self) -> Self {
         Self {
-            texture: Rc::new(i1.processor.add(&i1.texture, &i2.texture)),
-            processor: i1.processor,
+            texture: Rc::new(i1.backend.add(&i1.texture, &i2.texture)),
+            backend: i1.backend,
         }
     }
 
     pub fn mul(i1: &Self, i2: &Self) -> Self {
         Self {
-            texture: Rc::new(i1.processor.mul(&i1.texture, &i2.texture)),
-            processor: i1.processor,
+            texture: Rc::new(i1.backend.mul(&i1.texture, &i2.texture)),
+            backend: i1.backend,
         }
     }
 
     pub fn channels(r: &Self, g: &Self, b: &Self) -> Self {
         Self {
-            texture: Rc::new(r.processor.channels(&r.texture, &g.texture, &b.texture)),
-            processor: r.processor,
+            texture: Rc::new(r.backend.channels(&r.texture, &g.texture, &b.texture)),
+            backend: r.backend,
+        }
+    }
+}
+
+impl Image<'_> {
+    pub fn visualize(&self, fit: Fit) {
+        self.backend.visualize(&self.texture, fit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+
+    use super::Image;
+    use crate::backend::Backend;
+    use crate::color::TransferFunction;
+    use crate::error::Error;
+    use crate::output::{self, OutputFormat};
+    use crate::process::CpuProcessor;
+
+    #[test]
+    fn rgba32f_round_trip() {
+        let pixels: Vec<_> = (0..6).map(|i| [i as f32, -1.5, 20.0, 0.25 * i as f32]).collect();
+        let image = Image::from_rgba32f(&CpuProcessor, 3, 2, &pixels).unwrap();
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.to_rgba32f(), pixels);
+    }
+
+    #[test]
+    fn empty_to_rgba32f() {
+        for &(width, height) in &[(0, 0), (0, 3), (3, 0)] {
+            let texture = CpuProcessor.upload(width, height, Vec::new()).unwrap();
+            let image = Image { texture: Rc::new(texture), backend: &CpuProcessor };
+            assert!(image.to_rgba32f().is_empty());
+        }
+    }
+
+    #[test]
+    fn rgba32f_size_mismatch() {
+        let pixels = [[0.0; 4]; 6];
+        for &(width, height) in &[(4, 2), (0, 0), (u32::MAX, 2)] {
+            match Image::from_rgba32f(&CpuProcessor, width, height, &pixels) {
+                Err(Error::SizeMismatch(6, w, h)) => assert_eq!((w, h), (width, height)),
+                _ => panic!("{}x{} accepted 6 pixels", width, height),
+            }
+        }
+    }
+
+    /// Path in the temporary directory that no other test or test run uses
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("color_play_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn unsupported_profile_falls_back_to_srgb() {
+        let path = temp_path("unsupported_profile_falls_back_to_srgb.png");
+        let pixels = [[0.5, 0.25, 1.0, 1.0]];
+        output::write(&path, OutputFormat::Byte, 1, 1, &pixels, Some(b"not a profile")).unwrap();
+        let srgb = Image::new(&CpuProcessor, &path).unwrap().to_rgba32f();
+        for c in 0..3 {
+            let expected = TransferFunction::Srgb.decode(pixels[0][c]);
+            assert!((srgb[0][c] - expected).abs() < 0.01, "{:?}", srgb);
+        }
+        match Image::with_embedded_profile(&CpuProcessor, &path) {
+            Err(Error::Profile(..)) => (),
+            _ => panic!("Invalid profile accepted"),
+        }
+        let ignored = Image::with_transfer(&CpuProcessor, &path, TransferFunction::Srgb).unwrap();
+        assert_eq!(ignored.dimensions(), (1, 1));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn with_transfer_decodes_the_stored_values_once() {
+        let path = Path::new("images/rgb.png");
+        let raw = image::open(path).unwrap().to_rgb();
+        let srgb = Image::new(&CpuProcessor, path).unwrap().to_rgba32f();
+        let gamma = Image::with_transfer(&CpuProcessor, path, TransferFunction::Gamma(2.2))
+            .unwrap()
+            .to_rgba32f();
+        for ((srgb, gamma), raw) in srgb.iter().zip(&gamma).zip(raw.pixels()) {
+            for c in 0..3 {
+                let encoded = f32::from(raw[c]) / 255.0;
+                assert!((srgb[c] - TransferFunction::Srgb.decode(encoded)).abs() < 1e-4);
+                assert!((gamma[c] - encoded.powf(2.2)).abs() < 1e-4);
+            }
         }
     }
 }
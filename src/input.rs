@@ -0,0 +1,200 @@
+//! Readers for the embedded ICC profiles that the image crate skips
+
+use image::ImageFormat;
+
+fn u16_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = [*data.get(offset)?, *data.get(offset + 1)?];
+    Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+}
+
+fn u32_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = [
+        *data.get(offset)?,
+        *data.get(offset + 1)?,
+        *data.get(offset + 2)?,
+        *data.get(offset + 3)?,
+    ];
+    Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+}
+
+/// The embedded ICC profile of an image file, if it has one
+pub fn icc_profile(data: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    match format {
+        ImageFormat::PNG => png_profile(data),
+        ImageFormat::JPEG => jpeg_profile(data),
+        ImageFormat::TIFF => tiff_profile(data),
+        _ => None,
+    }
+}
+
+/// The iCCP chunk holds a name, a compression method and the zlib compressed profile
+fn png_profile(data: &[u8]) -> Option<Vec<u8>> {
+    let mut offset = 8;
+    while offset + 8 <= data.len() {
+        let length = u32_at(data, offset, true)? as usize;
+        let kind = &data[offset + 4..offset + 8];
+        let chunk = data.get(offset + 8..offset + 8 + length)?;
+        match kind {
+            b"iCCP" => {
+                let name_end = chunk.iter().position(|&b| b == 0)?;
+                return inflate::inflate_bytes_zlib(chunk.get(name_end + 2..)?).ok();
+            }
+            // The profile has to come before the image data
+            b"IDAT" | b"IEND" => return None,
+            _ => offset += 12 + length,
+        }
+    }
+    None
+}
+
+/// Profiles are split into APP2 segments tagged with ICC_PROFILE and their
+/// sequence number
+fn jpeg_profile(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+    let mut parts = Vec::new();
+    let mut offset = 2;
+    while offset + 4 <= data.len() && data[offset] == 0xFF {
+        let marker = data[offset + 1];
+        // The entropy coded data starts after start of scan
+        if marker == 0xDA {
+            break;
+        }
+        let length = usize::from(u16_at(data, offset + 2, true)?);
+        let segment = data.get(offset + 4..offset + 2 + length)?;
+        if marker == 0xE2 && segment.starts_with(SIGNATURE) && segment.len() > SIGNATURE.len() + 2 {
+            parts.push((segment[SIGNATURE.len()], &segment[SIGNATURE.len() + 2..]));
+        }
+        offset += 2 + length;
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    parts.sort_by_key(|&(sequence, _)| sequence);
+    Some(parts.into_iter().flat_map(|(_, part)| part.to_vec()).collect())
+}
+
+/// The profile is the InterColorProfile tag of the first image
+fn tiff_profile(data: &[u8]) -> Option<Vec<u8>> {
+    const ICC_PROFILE_TAG: u16 = 34675;
+    let big_endian = match data.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let ifd = u32_at(data, 4, big_endian)? as usize;
+    let n_entries = usize::from(u16_at(data, ifd, big_endian)?);
+    (0..n_entries)
+        .map(|i| ifd + 2 + 12 * i)
+        .find(|&entry| u16_at(data, entry, big_endian) == Some(ICC_PROFILE_TAG))
+        .and_then(|entry| {
+            let count = u32_at(data, entry + 4, big_endian)? as usize;
+            let offset = u32_at(data, entry + 8, big_endian)? as usize;
+            data.get(offset..offset + count).map(<[u8]>::to_vec)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ImageFormat;
+
+    use super::icc_profile;
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        // The reader does not check the crc
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn png(chunks: &[(&[u8], Vec<u8>)]) -> Vec<u8> {
+        let mut file = b"\x89PNG\r\n\x1a\n".to_vec();
+        file.extend(png_chunk(b"IHDR", &[0; 13]));
+        for (kind, data) in chunks {
+            file.extend(png_chunk(kind, data));
+        }
+        file.extend(png_chunk(b"IEND", &[]));
+        file
+    }
+
+    fn iccp(profile: &[u8]) -> Vec<u8> {
+        let mut data = b"ICC profile\0\0".to_vec();
+        data.extend(deflate::deflate_bytes_zlib(profile));
+        data
+    }
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    fn jpeg_icc(sequence: u8, count: u8, part: &[u8]) -> Vec<u8> {
+        let mut data = b"ICC_PROFILE\0".to_vec();
+        data.extend_from_slice(&[sequence, count]);
+        data.extend_from_slice(part);
+        jpeg_segment(0xE2, &data)
+    }
+
+    /// Tiff with a width tag and the profile tag in its first directory
+    fn tiff(big_endian: bool, profile: &[u8]) -> Vec<u8> {
+        let u16_bytes = |v: u16| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        let u32_bytes = |v: u32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        let mut file = if big_endian { b"MM".to_vec() } else { b"II".to_vec() };
+        file.extend_from_slice(&u16_bytes(42));
+        file.extend_from_slice(&u32_bytes(8));
+        file.extend_from_slice(&u16_bytes(2));
+        let profile_offset = 8 + 2 + 2 * 12 + 4;
+        let entries = [(256, 4, 1, 1), (34675, 7, profile.len() as u32, profile_offset)];
+        for &(tag, kind, count, value) in &entries {
+            file.extend_from_slice(&u16_bytes(tag));
+            file.extend_from_slice(&u16_bytes(kind));
+            file.extend_from_slice(&u32_bytes(count));
+            file.extend_from_slice(&u32_bytes(value));
+        }
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(profile);
+        file
+    }
+
+    #[test]
+    fn png_iccp() {
+        let profile: Vec<u8> = (0..=255).collect();
+        let file = png(&[(b"gAMA", vec![0; 4]), (b"iCCP", iccp(&profile)), (b"IDAT", vec![0; 8])]);
+        assert_eq!(icc_profile(&file, ImageFormat::PNG), Some(profile));
+        assert_eq!(icc_profile(&png(&[(b"IDAT", vec![0; 8])]), ImageFormat::PNG), None);
+        // Chunks after the image data are ignored
+        let late = png(&[(b"IDAT", vec![0; 8]), (b"iCCP", iccp(b"profile"))]);
+        assert_eq!(icc_profile(&late, ImageFormat::PNG), None);
+        // Truncated files have no profile instead of panicking
+        assert_eq!(icc_profile(&file[..40], ImageFormat::PNG), None);
+    }
+
+    #[test]
+    fn jpeg_segments_in_sequence_order() {
+        let mut file = vec![0xFF, 0xD8];
+        file.extend(jpeg_segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0"));
+        file.extend(jpeg_icc(2, 3, b"middle "));
+        file.extend(jpeg_icc(1, 3, b"first "));
+        file.extend(jpeg_segment(0xE2, b"Something else"));
+        file.extend(jpeg_icc(3, 3, b"last"));
+        file.extend(jpeg_segment(0xDA, &[0; 10]));
+        // Entropy coded data can contain anything
+        file.extend(jpeg_icc(4, 3, b" ignored"));
+        assert_eq!(icc_profile(&file, ImageFormat::JPEG), Some(b"first middle last".to_vec()));
+        assert_eq!(icc_profile(&[0xFF, 0xD8, 0xFF, 0xDA, 0, 2], ImageFormat::JPEG), None);
+    }
+
+    #[test]
+    fn tiff_either_byte_order() {
+        let profile = b"tiff profile".to_vec();
+        assert_eq!(icc_profile(&tiff(false, &profile), ImageFormat::TIFF), Some(profile.clone()));
+        assert_eq!(icc_profile(&tiff(true, &profile), ImageFormat::TIFF), Some(profile.clone()));
+        let mut truncated = tiff(true, &profile);
+        truncated.truncate(truncated.len() - 1);
+        assert_eq!(icc_profile(&truncated, ImageFormat::TIFF), None);
+        assert_eq!(icc_profile(b"XX\0\0", ImageFormat::TIFF), None);
+    }
+}
@@ -1,27 +1,55 @@
-use std::path::{PathBuf, Path};
+use std::env;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::Duration;
 
-use glium::glutin::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent, dpi::LogicalSize};
+use glium::glutin::{ElementState, Event, GlContext, KeyboardInput, VirtualKeyCode, WindowEvent, dpi::LogicalSize};
 
+mod backend;
+mod color;
+mod deck;
+mod diagram;
+mod error;
 mod image;
+mod input;
+mod output;
 mod presentation;
 mod process;
+mod render;
 mod scene;
 
-use self::image::Image;
+use self::deck::Deck;
 use self::presentation::Presentation;
 use self::process::Processor;
 
-/// Convert u8 color to float color in range [0, 1]
+/// Convert u8 sRGB color to linear float color in range [0, 1]
 pub fn srgb_to_float(c: u8) -> f32 {
-    (f32::from(c) / 255.0).powf(2.2)
+    color::srgb_decode(f32::from(c) / 255.0)
 }
 
-#[allow(clippy::single_match, unused_variables)]
+const WIDTH: u32 = 1536;
+const HEIGHT: u32 = 864;
+
 fn main() {
-    let width = 1536;
-    let height = 864;
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("render") => render::run(&args[1..]),
+        Some(deck) => present(&PathBuf::from(deck)),
+        None => present(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("decks").join("default.toml")),
+    }
+}
+
+/// Run the interactive presentation described by the deck file
+fn present(deck: &Path) {
+    let deck = match Deck::load(deck) {
+        Ok(deck) => deck,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let width = WIDTH;
+    let height = HEIGHT;
     let mut events_loop = glium::glutin::EventsLoop::new();
     let window = glium::glutin::WindowBuilder::new()
         .with_dimensions(LogicalSize::new(f64::from(width), f64::from(height)));
@@ -30,21 +58,28 @@ fn main() {
         glium::Display::new(window, context, &events_loop).expect("Failed to create display");
     let mut fullscreen = false;
 
-    let processor = Processor::new(&display, width, height);
-    let root_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let output_dir = root_dir.join("results");
-    std::fs::create_dir_all(output_dir.clone()).unwrap();
-    let image_dir = root_dir.join("images");
-    let mut presentation = Presentation::new(&processor, &image_dir);
+    let processor = Processor::new(&display);
+    let mut presentation = Presentation::new(&processor, &deck);
+    if presentation.is_empty() {
+        eprintln!("No scenes to present");
+        return;
+    }
 
     loop {
-        presentation.image().visualize();
+        presentation.image().visualize(presentation.fit());
         let mut quit = false;
         events_loop.poll_events(|event| match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => quit = true,
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => {
+                let window = display.gl_window();
+                window.resize(size.to_physical(window.get_hidpi_factor()));
+            }
             Event::WindowEvent {
                 event: WindowEvent::KeyboardInput { input, .. },
                 ..
@@ -74,6 +109,11 @@ fn main() {
                     virtual_keycode: Some(VirtualKeyCode::Left),
                     ..
                 } => presentation.previous_scene(),
+                KeyboardInput {
+                    state: ElementState::Released,
+                    virtual_keycode: Some(VirtualKeyCode::M),
+                    ..
+                } => presentation.next_fit(),
                 KeyboardInput {
                     state: ElementState::Released,
                     virtual_keycode: Some(VirtualKeyCode::F),
@@ -95,30 +135,10 @@ fn main() {
         if quit {
             return;
         }
+        // Covers resizing, fullscreen and HiDPI factor changes
+        if processor.resize() {
+            presentation.resize();
+        }
         thread::sleep(Duration::from_millis(100));
     }
 }
-
-#[allow(dead_code)]
-fn mix_chroma_luma<'a>(tex1: &'a Image, tex2: &'a Image) -> Image<'a> {
-    let chroma = tex1.rgb_to_xyz();
-    let luma = tex2.rgb_to_xyz();
-    Image::channels(&chroma, &luma, &chroma).xyz_to_rgb()
-}
-
-#[allow(dead_code)]
-fn luma_random_mixes<'a>(tex: &'a Image, dir: &Path) {
-    let random = Image::random(tex.processor).rgb_to_xyz();
-    let luma = tex.rgb_to_xyz();
-    Image::channels(&random, &random, &luma).xyz_to_rgb().save(&dir.join("random_xy.png"));
-    Image::channels(&luma, &random, &random).xyz_to_rgb().save(&dir.join("random_yz.png"));
-    Image::channels(&random, &luma, &random).xyz_to_rgb().save(&dir.join("random_xz.png"));
-    Image::channels(&luma, &random, &luma).xyz_to_rgb().save(&dir.join("random_y.png"));
-}
-
-#[allow(dead_code)]
-fn pink_scale<'a>(tex: &'a Image, dir: &Path) {
-    let pink = Image::monochrome(tex.processor, srgb_to_float(255), srgb_to_float(145), srgb_to_float(175));
-    let scale = tex.rgb_to_xyz().single_channel(2);
-    Image::mul(&pink, &scale).save(&dir.join("pink_scale.png"));
-}
@@ -0,0 +1,564 @@
+//! Writers for 16 bit and float image files
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use image::hdr::HDREncoder;
+use image::png::PNGEncoder;
+use image::{ColorType, Rgb};
+
+use crate::color::{IccProfile, TransferFunction, SRGB};
+
+/// File format of a saved image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// 8 bits per channel in the format of the extension, for example png or jpg
+    Byte,
+    /// 16 bits per channel png
+    Png16,
+    /// 16 bits per channel uncompressed tiff
+    Tiff16,
+    /// 32 bit float OpenEXR without compression
+    Exr,
+    /// Radiance rgbe, which has no negative values
+    Hdr,
+    /// 32 bit float portable float map
+    Pfm,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 6] = [
+        OutputFormat::Byte,
+        OutputFormat::Png16,
+        OutputFormat::Tiff16,
+        OutputFormat::Exr,
+        OutputFormat::Hdr,
+        OutputFormat::Pfm,
+    ];
+
+    /// Format for the extension of `path`. The image crate can't write tiff, so
+    /// tiff files get 16 bits.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("tif") | Some("tiff") => OutputFormat::Tiff16,
+            Some("exr") => OutputFormat::Exr,
+            Some("hdr") => OutputFormat::Hdr,
+            Some("pfm") => OutputFormat::Pfm,
+            _ => OutputFormat::Byte,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Byte => "png",
+            OutputFormat::Png16 => "png16",
+            OutputFormat::Tiff16 => "tiff16",
+            OutputFormat::Exr => "exr",
+            OutputFormat::Hdr => "hdr",
+            OutputFormat::Pfm => "pfm",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Byte | OutputFormat::Png16 => "png",
+            OutputFormat::Tiff16 => "tiff",
+            OutputFormat::Exr => "exr",
+            OutputFormat::Hdr => "hdr",
+            OutputFormat::Pfm => "pfm",
+        }
+    }
+
+    /// Float formats store linear values unless told otherwise
+    pub fn is_float(self) -> bool {
+        match self {
+            OutputFormat::Exr | OutputFormat::Hdr | OutputFormat::Pfm => true,
+            OutputFormat::Byte | OutputFormat::Png16 | OutputFormat::Tiff16 => false,
+        }
+    }
+}
+
+/// How `Image::save_with` writes a file
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SaveOptions {
+    /// Format to use instead of the one of the extension
+    pub format: Option<OutputFormat>,
+    /// Encoding of the stored values. By default float formats are linear and the
+    /// others sRGB.
+    pub transfer: Option<TransferFunction>,
+    /// Describe the stored values with an ICC profile of sRGB, or of the linear
+    /// working space when they are linear. EXR gets the sRGB chromaticities
+    /// instead, while Radiance and PFM files have no place for a profile.
+    pub embed_profile: bool,
+}
+
+impl SaveOptions {
+    pub fn format(&self, path: &Path) -> OutputFormat {
+        self.format.unwrap_or_else(|| OutputFormat::from_path(path))
+    }
+
+    pub fn transfer(&self, format: OutputFormat) -> TransferFunction {
+        self.transfer.unwrap_or(if format.is_float() {
+            TransferFunction::Linear
+        } else {
+            TransferFunction::Srgb
+        })
+    }
+
+    /// Profile to embed for the values stored with `transfer`
+    pub fn profile(&self, transfer: TransferFunction) -> Option<Vec<u8>> {
+        if !self.embed_profile {
+            return None;
+        }
+        let description = match transfer {
+            TransferFunction::Srgb => "sRGB".to_owned(),
+            TransferFunction::Linear => "Linear sRGB".to_owned(),
+            transfer => format!("sRGB primaries with {:?} encoding", transfer),
+        };
+        Some(IccProfile::write(&SRGB, transfer, &description))
+    }
+}
+
+/// Write rgb of `pixels` given with rows from bottom to top. Values are stored as
+/// they are, except that the integer formats clamp them to [0, 1]. `profile` is
+/// embedded in png, jpeg and tiff files and marks EXR files as sRGB.
+pub fn write(
+    path: &Path,
+    format: OutputFormat,
+    width: u32,
+    height: u32,
+    pixels: &[[f32; 4]],
+    profile: Option<&[u8]>,
+) -> io::Result<()> {
+    let rows_top_first = || pixels.chunks(width as usize).rev();
+    match format {
+        OutputFormat::Byte => {
+            let data: Vec<u8> = rows_top_first()
+                .flatten()
+                .flat_map(|p| p[..3].iter().map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+                .collect();
+            image::save_buffer(path, &data, width, height, ColorType::RGB(8))?;
+            match profile {
+                // The encoders of the image crate can't embed profiles
+                Some(profile) => fs::write(path, embed_profile(fs::read(path)?, profile)),
+                None => Ok(()),
+            }
+        }
+        OutputFormat::Png16 => {
+            let data: Vec<u8> = rows_top_first()
+                .flatten()
+                .flat_map(|p| p[..3].iter().flat_map(|&c| to_u16(c).to_be_bytes()))
+                .collect();
+            let mut png = Vec::new();
+            PNGEncoder::new(&mut png).encode(&data, width, height, ColorType::RGB(16))?;
+            match profile {
+                Some(profile) => fs::write(path, embed_profile(png, profile)),
+                None => fs::write(path, png),
+            }
+        }
+        OutputFormat::Tiff16 => fs::write(path, tiff16(width, height, rows_top_first().flatten(), profile)?),
+        OutputFormat::Exr => fs::write(path, exr(width, height, pixels, profile.is_some())),
+        OutputFormat::Hdr => {
+            let data: Vec<_> = rows_top_first()
+                .flatten()
+                .map(|p| Rgb { data: [p[0].max(0.0), p[1].max(0.0), p[2].max(0.0)] })
+                .collect();
+            let file = io::BufWriter::new(fs::File::create(path)?);
+            HDREncoder::new(file).encode(&data, width as usize, height as usize)
+        }
+        OutputFormat::Pfm => {
+            // Negative scale means little endian. Rows go from bottom to top.
+            let mut data = format!("PF\n{} {}\n-1.0\n", width, height).into_bytes();
+            for p in pixels {
+                for c in &p[..3] {
+                    data.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            fs::write(path, data)
+        }
+    }
+}
+
+fn to_u16(c: f32) -> u16 {
+    (c.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+/// CRC-32 of png chunks
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Insert `profile` into an encoded png or jpeg file. Other files are returned as
+/// they are.
+fn embed_profile(mut file: Vec<u8>, profile: &[u8]) -> Vec<u8> {
+    if file.starts_with(b"\x89PNG") {
+        // iCCP chunk with a name and the zlib compressed profile right after IHDR
+        let mut chunk = b"iCCPICC profile\0\0".to_vec();
+        chunk.extend_from_slice(&deflate::deflate_bytes_zlib(profile));
+        let mut bytes = ((chunk.len() - 4) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&chunk);
+        bytes.extend_from_slice(&crc32(&chunk).to_be_bytes());
+        let ihdr_end = 8 + 12 + 13;
+        file.splice(ihdr_end..ihdr_end, bytes);
+    } else if file.starts_with(&[0xFF, 0xD8]) {
+        // APP2 segments numbered from 1 of at most 64 KiB each, after the JFIF header
+        const MAX_PART: usize = 65_519;
+        let n_parts = profile.len().div_ceil(MAX_PART);
+        let mut segments = Vec::new();
+        for (i, part) in profile.chunks(MAX_PART).enumerate() {
+            segments.extend_from_slice(&[0xFF, 0xE2]);
+            segments.extend_from_slice(&((part.len() + 16) as u16).to_be_bytes());
+            segments.extend_from_slice(b"ICC_PROFILE\0");
+            segments.extend_from_slice(&[i as u8 + 1, n_parts as u8]);
+            segments.extend_from_slice(part);
+        }
+        let mut offset = 2;
+        if file.get(offset..offset + 2) == Some(&[0xFF, 0xE0]) {
+            offset += 2 + usize::from(u16::from_be_bytes([file[offset + 2], file[offset + 3]]));
+        }
+        file.splice(offset..offset, segments);
+    }
+    file
+}
+
+/// Uncompressed little endian baseline tiff with 16 bit rgb in a single strip,
+/// followed by the profile. Offsets are 32 bit, so the file has to fit in 4 GiB.
+fn tiff16<'a>(
+    width: u32,
+    height: u32,
+    pixels: impl Iterator<Item = &'a [f32; 4]>,
+    profile: Option<&[u8]>,
+) -> io::Result<Vec<u8>> {
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    const RATIONAL: u16 = 5;
+    const UNDEFINED: u16 = 7;
+    let entries = if profile.is_some() { 14 } else { 13 };
+    let ifd_size = 2 + 12 * entries + 4;
+    let bits_offset = 8 + ifd_size;
+    let resolution_offset = bits_offset + 6;
+    let data_offset = resolution_offset + 16;
+    let strip_size = 6 * width as usize * height as usize;
+    let profile_offset = data_offset + strip_size;
+    let size = profile_offset + profile.map_or(0, <[u8]>::len);
+    if size > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Image too large for a tiff file"));
+    }
+
+    let mut data = Vec::new();
+    data.extend_from_slice(b"II");
+    data.extend_from_slice(&42u16.to_le_bytes());
+    data.extend_from_slice(&8u32.to_le_bytes());
+    data.extend_from_slice(&(entries as u16).to_le_bytes());
+    // All sizes and offsets fit in 32 bits because the file does
+    let mut entry = |tag: u16, kind: u16, count: usize, value: usize| {
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&kind.to_le_bytes());
+        data.extend_from_slice(&(count as u32).to_le_bytes());
+        data.extend_from_slice(&(value as u32).to_le_bytes());
+    };
+    entry(256, LONG, 1, width as usize);
+    entry(257, LONG, 1, height as usize);
+    entry(258, SHORT, 3, bits_offset);
+    // No compression
+    entry(259, SHORT, 1, 1);
+    // Rgb
+    entry(262, SHORT, 1, 2);
+    entry(273, LONG, 1, data_offset);
+    entry(277, SHORT, 1, 3);
+    entry(278, LONG, 1, height as usize);
+    entry(279, LONG, 1, strip_size);
+    entry(282, RATIONAL, 1, resolution_offset);
+    entry(283, RATIONAL, 1, resolution_offset + 8);
+    // Chunky rgb
+    entry(284, SHORT, 1, 1);
+    // Resolution in inches
+    entry(296, SHORT, 1, 2);
+    if let Some(profile) = profile {
+        entry(34675, UNDEFINED, profile.len(), profile_offset);
+    }
+    data.extend_from_slice(&0u32.to_le_bytes());
+
+    for _ in 0..3 {
+        data.extend_from_slice(&16u16.to_le_bytes());
+    }
+    for _ in 0..2 {
+        data.extend_from_slice(&72u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+    }
+    for p in pixels {
+        for &c in &p[..3] {
+            data.extend_from_slice(&to_u16(c).to_le_bytes());
+        }
+    }
+    if let Some(profile) = profile {
+        data.extend_from_slice(profile);
+    }
+    Ok(data)
+}
+
+/// Scanline OpenEXR with uncompressed 32 bit float rgb. `pixels` has rows from
+/// bottom to top and EXR from top to bottom. Without chromaticities readers
+/// assume sRGB primaries anyway.
+fn exr(width: u32, height: u32, pixels: &[[f32; 4]], chromaticities: bool) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&20_000_630u32.to_le_bytes());
+    // Version 2 of a single part scanline file
+    data.extend_from_slice(&2u32.to_le_bytes());
+    let attribute = |data: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]| {
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+        data.extend_from_slice(kind.as_bytes());
+        data.push(0);
+        data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        data.extend_from_slice(value);
+    };
+
+    // Channels are listed in alphabetical order
+    let mut channels = Vec::new();
+    for name in b"BGR" {
+        channels.extend_from_slice(&[*name, 0]);
+        // Float pixels, not perceptually linear and 3 reserved bytes
+        channels.extend_from_slice(&2u32.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        // No subsampling
+        channels.extend_from_slice(&1u32.to_le_bytes());
+        channels.extend_from_slice(&1u32.to_le_bytes());
+    }
+    channels.push(0);
+    attribute(&mut data, "channels", "chlist", &channels);
+    if chromaticities {
+        let [r, g, b] = SRGB.primaries;
+        let xy: Vec<u8> = [r, g, b, SRGB.white]
+            .iter()
+            .flatten()
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect();
+        attribute(&mut data, "chromaticities", "chromaticities", &xy);
+    }
+    attribute(&mut data, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect();
+    attribute(&mut data, "dataWindow", "box2i", &window);
+    attribute(&mut data, "displayWindow", "box2i", &window);
+    // Increasing y
+    attribute(&mut data, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut data, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute(&mut data, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut data, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    data.push(0);
+
+    // Offsets of the scanlines, each with its y, byte count and the channels one
+    // after another
+    let line_size = 3 * 4 * width as usize;
+    let first_line = data.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        data.extend_from_slice(&((first_line + y * (8 + line_size)) as u64).to_le_bytes());
+    }
+    for (y, row) in pixels.chunks(width as usize).rev().enumerate() {
+        data.extend_from_slice(&(y as i32).to_le_bytes());
+        data.extend_from_slice(&(line_size as u32).to_le_bytes());
+        for channel in (0..3).rev() {
+            for p in row {
+                data.extend_from_slice(&p[channel].to_le_bytes());
+            }
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use image::hdr::HDRDecoder;
+    use image::jpeg::JPEGEncoder;
+    use image::png::PNGEncoder;
+    use image::{ColorType, ImageFormat};
+
+    use super::{crc32, embed_profile, write, OutputFormat};
+    use crate::input;
+
+    /// 2x2 image with rows from bottom to top, with values outside [0, 1] and
+    /// transparent pixels
+    const PIXELS: [[f32; 4]; 4] = [
+        [0.0, 0.25, 0.5, 1.0],
+        [1.0, 0.75, -0.5, 0.0],
+        [2.0, 0.125, 0.0, 1.0],
+        [0.5, 0.5, 0.5, 0.0],
+    ];
+
+    /// Path in the temporary directory that no other test or test run uses
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("color_play_{}_{}", std::process::id(), name))
+    }
+
+    /// Write `PIXELS` as `format` and read the file back
+    fn written(name: &str, format: OutputFormat, profile: Option<&[u8]>) -> Vec<u8> {
+        let path = temp_path(name);
+        write(&path, format, 2, 2, &PIXELS, profile).unwrap();
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        data
+    }
+
+    /// `PIXELS` from top to bottom
+    fn rows_top_first() -> impl Iterator<Item = &'static [f32; 4]> {
+        PIXELS.chunks(2).rev().flatten()
+    }
+
+    fn u16_le(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn u32_le(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+    }
+
+    fn f32_le(data: &[u8], offset: usize) -> f32 {
+        f32::from_bits(u32_le(data, offset))
+    }
+
+    #[test]
+    fn byte_is_opaque_rgb() {
+        let data = written("byte_is_opaque_rgb.png", OutputFormat::Byte, None);
+        let image = image::load_from_memory_with_format(&data, ImageFormat::PNG).unwrap();
+        assert_eq!(image.color(), ColorType::RGB(8));
+        let expected: Vec<u8> = rows_top_first()
+            .flat_map(|p| p[..3].iter().map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect();
+        assert_eq!(image.to_rgb().into_raw(), expected);
+    }
+
+    #[test]
+    fn pfm_keeps_values_bottom_to_top() {
+        let data = written("pfm_keeps_values_bottom_to_top.pfm", OutputFormat::Pfm, None);
+        let header = b"PF\n2 2\n-1.0\n";
+        assert_eq!(&data[..header.len()], header);
+        assert_eq!(data.len(), header.len() + 4 * 3 * 4);
+        for (i, p) in PIXELS.iter().enumerate() {
+            for (c, &value) in p[..3].iter().enumerate() {
+                assert_eq!(f32_le(&data, header.len() + 4 * (3 * i + c)), value);
+            }
+        }
+    }
+
+    #[test]
+    fn hdr_decodes_to_the_clamped_values() {
+        let data = written("hdr_decodes_to_the_clamped_values.hdr", OutputFormat::Hdr, None);
+        let decoder = HDRDecoder::new(&data[..]).unwrap();
+        let metadata = decoder.metadata();
+        assert_eq!((metadata.width, metadata.height), (2, 2));
+        let pixels = decoder.read_image_hdr().unwrap();
+        for (pixel, expected) in pixels.iter().zip(rows_top_first()) {
+            for c in 0..3 {
+                // Shared exponents keep about 8 bits of the largest channel
+                let expected = expected[c].max(0.0);
+                assert!((pixel.data[c] - expected).abs() < 0.01, "{:?} != {:?}", pixel, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn exr_scanlines() {
+        let data = written("exr_scanlines.exr", OutputFormat::Exr, Some(b"profile"));
+        assert_eq!(u32_le(&data, 0), 20_000_630);
+        // Attributes are a name, a type, a size and the value, up to an empty name
+        let mut offset = 8;
+        let mut names = Vec::new();
+        while data[offset] != 0 {
+            let name_end = offset + data[offset..].iter().position(|&b| b == 0).unwrap();
+            let kind_end = name_end + 1 + data[name_end + 1..].iter().position(|&b| b == 0).unwrap();
+            names.push(String::from_utf8_lossy(&data[offset..name_end]).into_owned());
+            offset = kind_end + 5 + u32_le(&data, kind_end + 1) as usize;
+        }
+        assert!(names.iter().any(|name| name == "chromaticities"), "{:?}", names);
+        let offsets = offset + 1;
+        for y in 0..2 {
+            let line = u32_le(&data, offsets + 8 * y) as usize;
+            assert_eq!(u32_le(&data, line), y as u32);
+            assert_eq!(u32_le(&data, line + 4), 2 * 3 * 4);
+            // Channels one after another in the order b, g and r
+            for (x, pixel) in rows_top_first().skip(2 * y).take(2).enumerate() {
+                for (i, c) in [2, 1, 0].iter().enumerate() {
+                    assert_eq!(f32_le(&data, line + 8 + 4 * (2 * i + x)), pixel[*c]);
+                }
+            }
+        }
+        let plain = written("exr_scanlines_plain.exr", OutputFormat::Exr, None);
+        assert!(!plain.windows(14).any(|w| w == b"chromaticities"));
+    }
+
+    #[test]
+    fn tiff16_tags_and_strip() {
+        let profile = b"profile!";
+        let data = written("tiff16_tags_and_strip.tiff", OutputFormat::Tiff16, Some(profile));
+        assert_eq!(&data[..4], b"II*\0");
+        let ifd = u32_le(&data, 4) as usize;
+        let n_entries = usize::from(u16_le(&data, ifd));
+        let tag = |tag: u16| {
+            (0..n_entries)
+                .map(|i| ifd + 2 + 12 * i)
+                .find(|&entry| u16_le(&data, entry) == tag)
+                .map(|entry| (u32_le(&data, entry + 4) as usize, u32_le(&data, entry + 8) as usize))
+                .unwrap()
+        };
+        assert_eq!(tag(256), (1, 2));
+        assert_eq!(tag(257), (1, 2));
+        let (_, strip) = tag(273);
+        assert_eq!(tag(279), (1, 2 * 2 * 6));
+        for (i, pixel) in rows_top_first().enumerate() {
+            for (c, &value) in pixel[..3].iter().enumerate() {
+                let expected = (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+                assert_eq!(u16_le(&data, strip + 2 * (3 * i + c)), expected);
+            }
+        }
+        let (count, offset) = tag(34675);
+        assert_eq!(&data[offset..offset + count], profile);
+        assert_eq!(input::icc_profile(&data, ImageFormat::TIFF), Some(profile.to_vec()));
+    }
+
+    #[test]
+    fn crc_of_iend() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn png_profile_after_ihdr() {
+        let mut png = Vec::new();
+        PNGEncoder::new(&mut png).encode(&[10, 20, 30, 40, 50, 60], 2, 1, ColorType::RGB(8)).unwrap();
+        let profile: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let file = embed_profile(png, &profile);
+        assert_eq!(&file[37..41], b"iCCP");
+        assert_eq!(input::icc_profile(&file, ImageFormat::PNG), Some(profile));
+        // The decoder checks the crc of every chunk
+        let decoded = image::load_from_memory_with_format(&file, ImageFormat::PNG).unwrap().to_rgb();
+        assert_eq!(decoded.into_raw(), vec![10, 20, 30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn jpeg_profile_split_into_segments() {
+        let mut jpeg = Vec::new();
+        JPEGEncoder::new(&mut jpeg).encode(&[128; 8 * 8 * 3], 8, 8, ColorType::RGB(8)).unwrap();
+        // Three segments, the last one partial
+        let profile: Vec<u8> = (0..150_000).map(|i| (i % 253) as u8).collect();
+        let file = embed_profile(jpeg, &profile);
+        let n_segments = file.windows(12).filter(|w| w == b"ICC_PROFILE\0").count();
+        assert_eq!(n_segments, 3);
+        assert_eq!(input::icc_profile(&file, ImageFormat::JPEG), Some(profile));
+        let decoded = image::load_from_memory_with_format(&file, ImageFormat::JPEG).unwrap();
+        assert_eq!(decoded.to_rgb().dimensions(), (8, 8));
+    }
+}
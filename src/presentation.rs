@@ -1,65 +1,237 @@
+use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use cgmath::Vector3;
 
+use crate::deck::{Deck, Generator, ImageSpec, SceneSpec};
+use crate::diagram::Diagram;
+use crate::error::{Error, Result};
 use crate::image::Image;
-use crate::process::Processor;
+use crate::process::{Fit, Processor};
 use crate::scene::Scene;
 
+/// Creates the images of a deck. Files shared by several scenes are only loaded once.
+struct Loader<'a> {
+    processor: &'a Processor,
+    dir: &'a Path,
+    cache: HashMap<PathBuf, Image<'a>>,
+}
+
+impl<'a> Loader<'a> {
+    fn image(&mut self, spec: &ImageSpec) -> Result<Image<'a>> {
+        let processor = self.processor;
+        let (width, height) = processor.size();
+        let image = match spec {
+            ImageSpec::File(path) => {
+                let path = self.dir.join(path);
+                if let Some(image) = self.cache.get(&path) {
+                    return Ok(image.clone());
+                }
+                let image = Image::new(processor, &path)?;
+                self.cache.insert(path, image.clone());
+                image
+            }
+            ImageSpec::Generated(Generator::Random) => Image::random(processor, width, height)?,
+            ImageSpec::Generated(Generator::Rgb) => Image::rgb(processor, width, height)?,
+            ImageSpec::Decoded { file, transfer } => {
+                Image::with_transfer(processor, &self.dir.join(file), *transfer)?
+            }
+            ImageSpec::Generated(Generator::Gamma { transfer }) => {
+                Image::gamma(processor, width, height, *transfer)?
+            }
+            ImageSpec::Generated(Generator::Grayscale { value }) => {
+                Image::grayscale(processor, *value)?
+            }
+            ImageSpec::Generated(Generator::Chromaticity { observer, coordinates, spaces, scatter }) => {
+                let diagram = Diagram {
+                    observer: *observer,
+                    coordinates: *coordinates,
+                    spaces: spaces.clone(),
+                };
+                let scatter = match scatter {
+                    Some(path) => Some(self.image(&ImageSpec::File(path.clone()))?),
+                    None => None,
+                };
+                Image::chromaticity_diagram(processor, width, height, &diagram, scatter.as_ref())?
+            }
+            ImageSpec::Generated(Generator::Monochrome { color: [r, g, b] }) => {
+                Image::monochrome(processor, *r, *g, *b)?
+            }
+        };
+        Ok(image)
+    }
+
+    fn scene(&mut self, spec: &SceneSpec) -> Result<Scene<'a>> {
+        let scene = match spec {
+            SceneSpec::Plain { image } => Scene::plain(self.image(image)?),
+            SceneSpec::Permutation { images, permutation, space } => {
+                if images.is_empty() {
+                    return Err(Error::InvalidScene("No images to permute".to_string()));
+                }
+                if permutation.iter().any(|&c| c > 2) {
+                    return Err(Error::InvalidScene(format!("Invalid permutation {:?}", permutation)));
+                }
+                let images = images
+                    .iter()
+                    .map(|image| self.image(image))
+                    .collect::<Result<_>>()?;
+                Scene::permutation(images, Vector3::from(*permutation), *space)
+            }
+            SceneSpec::Channels { images: [r, g, b], space } => {
+                Scene::channels([self.image(r)?, self.image(g)?, self.image(b)?], *space)
+            }
+            SceneSpec::Combination { steps, images: [image1, image2] } => {
+                if *steps < 2 {
+                    return Err(Error::InvalidScene("Combination needs at least 2 steps".to_string()));
+                }
+                Scene::combination(*steps, self.image(image1)?, self.image(image2)?)
+            }
+            SceneSpec::Deficiencies { image, model, severity } => {
+                if !(0.0..=1.0).contains(severity) {
+                    return Err(Error::InvalidScene(format!("Severity {} is not in [0, 1]", severity)));
+                }
+                Scene::deficiencies(self.image(image)?, *model, *severity)
+            }
+            SceneSpec::Exposure { image, operator, white, stops } => {
+                let white = white.unwrap_or_else(|| operator.default_white());
+                if white <= 0.0 {
+                    return Err(Error::InvalidScene(format!("White point {} is not positive", white)));
+                }
+                Scene::exposure(self.image(image)?, *operator, white, *stops)
+            }
+            SceneSpec::Gamut { image, chroma, warning } => {
+                Scene::gamut(self.image(image)?, *chroma, Vector3::from(*warning))
+            }
+            SceneSpec::Hue { steps, image } => {
+                if *steps == 0 {
+                    return Err(Error::InvalidScene("Hue needs at least 1 step".to_string()));
+                }
+                Scene::hue(*steps, self.image(image)?)
+            }
+            SceneSpec::Illuminants { image, method } => {
+                Scene::illuminants(self.image(image)?, *method)
+            }
+            SceneSpec::IlluminantMetamerism { matched, other } => {
+                let (width, height) = self.processor.size();
+                let (matched, other) = (matched.spectrum(), other.spectrum());
+                Scene::illuminant_metamerism(self.processor, width, height, matched, other)?
+            }
+            SceneSpec::ObserverMetamerism { light } => {
+                let (width, height) = self.processor.size();
+                Scene::observer_metamerism(self.processor, width, height, light.spectrum())?
+            }
+            SceneSpec::Movement { mask } => Scene::movement(self.image(mask)?)?,
+            SceneSpec::Spaces { image } => Scene::spaces(self.image(image)?),
+            SceneSpec::Subsampling { image, standard } => {
+                Scene::subsampling(self.image(image)?, *standard)
+            }
+        };
+        Ok(scene)
+    }
+}
+
+struct Slide<'a> {
+    /// Index of the scene in the deck
+    index: usize,
+    scene: Scene<'a>,
+    keep_view: bool,
+    fit: Fit,
+    /// The processor size changed since the scene was built
+    stale: bool,
+}
+
 pub struct Presentation<'a> {
     i: usize,
-    scenes: Vec<(Scene<'a>, bool)>,
+    deck: &'a Deck,
+    loader: Loader<'a>,
+    slides: Vec<Slide<'a>>,
 }
 
 impl<'a> Presentation<'a> {
-    pub fn new(processor: &'a Processor, dir: &Path) -> Self {
-        let mut images = Vec::new();
-        images.push(Image::new(processor, &dir.join("nature.png")));
-        images.push(Image::new(processor, &dir.join("urban.png")));
-        images.push(Image::new(processor, &dir.join("people.jpg")));
-
-        let mut scenes = Vec::new();
-        // Intro images
-        scenes.push((Scene::plain(Image::rgb(processor)), false));
-        scenes.push((Scene::plain(Image::new(processor, &dir.join("xyz.png"))), false));
-        // scenes.push((Scene::plain(Image::new(processor, &dir.join("rgb.png"))), false));
-        scenes.push((Scene::plain(Image::new(processor, &dir.join("triangle.png"))), false));
-        // scenes.push((Scene::plain(Image::gamma(processor)), false));
-
-        // Permutations
-        scenes.push((Scene::permutation(images.clone(), Vector3::new(0, 1, 2)), false));
-        scenes.push((Scene::permutation(images.clone(), Vector3::new(0, 2, 1)), true));
-        scenes.push((Scene::permutation(images.clone(), Vector3::new(2, 1, 0)), true));
-        scenes.push((Scene::permutation(images.clone(), Vector3::new(1, 0, 2)), true));
-
-        // Channels
-        scenes.push((Scene::channels([images[0].clone(),
-                                     images[1].clone(),
-                                     images[2].clone()]), false));
-
-        // Combinations
-        let hidden = Image::new(&processor, &dir.join("sibelius.jpg"));
-        let n = 21;
-        scenes.push((Scene::combination(n, hidden.clone(), Image::random(processor)), false));
-        scenes.push((Scene::combination(n, hidden.clone(), Image::grayscale(processor, 1.0)), true));
-        scenes.push((Scene::combination(n, hidden.clone(), Image::grayscale(processor, 0.0)), true));
-
-        // Movement
-        scenes.push((Scene::movement(&processor, dir), false));
+    /// Build the scenes of `deck`. Scenes that fail to load are reported and skipped.
+    pub fn new(processor: &'a Processor, deck: &'a Deck) -> Self {
+        let mut loader = Loader {
+            processor,
+            dir: &deck.dir,
+            cache: HashMap::new(),
+        };
+        let mut slides = Vec::new();
+        for (index, slide) in deck.scenes.iter().enumerate() {
+            match loader.scene(&slide.scene) {
+                Ok(scene) => slides.push(Slide {
+                    index,
+                    scene,
+                    keep_view: slide.keep_view,
+                    fit: slide.fit,
+                    stale: false,
+                }),
+                Err(err) => eprintln!("Skipping scene {}: {}", index, err),
+            }
+        }
         Self {
             i: 0,
-            scenes,
+            deck,
+            loader,
+            slides,
+        }
+    }
+
+    /// Rebuild the current scene so that generated images match the processor
+    /// size. The other scenes are rebuilt when they are shown. Views and fit
+    /// modes are kept.
+    pub fn resize(&mut self) {
+        for slide in &mut self.slides {
+            slide.stale = true;
+        }
+        self.rebuild(self.i);
+    }
+
+    fn rebuild(&mut self, i: usize) {
+        let slide = &mut self.slides[i];
+        if !slide.stale {
+            return;
+        }
+        slide.stale = false;
+        match self.loader.scene(&self.deck.scenes[slide.index].scene) {
+            Ok(mut scene) => {
+                scene.set_view(slide.scene.current_view());
+                slide.scene = scene;
+            }
+            Err(err) => eprintln!("Failed to rebuild scene {}: {}", slide.index, err),
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.slides.is_empty()
+    }
+
+    pub fn fit(&self) -> Fit {
+        self.slides[self.i].fit
+    }
+
+    /// Switch the current scene to the next fit mode
+    pub fn next_fit(&mut self) {
+        let fit = &mut self.slides[self.i].fit;
+        *fit = fit.next();
+        println!("Fit: {:?}", fit);
+    }
+
+    /// Show the view of slide `from` in slide `to`, or its last view if it has
+    /// fewer views
+    fn copy_view(&mut self, from: usize, to: usize) {
+        let view = self.slides[from].scene.current_view();
+        let scene = &mut self.slides[to].scene;
+        let last = scene.n_views().saturating_sub(1);
+        scene.set_view(view.min(last));
+    }
+
     pub fn next_scene(&mut self) {
         let i = self.i + 1;
-        if i < self.scenes.len() {
-            let (_, keep_view) = self.scenes[i];
-            if keep_view {
-                let view = self.scenes[self.i].0.current_view();
-                self.scenes[i].0.set_view(view);
+        if i < self.slides.len() {
+            self.rebuild(i);
+            if self.slides[i].keep_view {
+                self.copy_view(self.i, i);
             }
             self.i = i;
             println!("Scene: {}", self.i);
@@ -72,10 +244,9 @@ impl<'a> Presentation<'a> {
         if self.i > 0 {
             let i = self.i;
             self.i -= 1;
-            let (_, keep_view) = self.scenes[i];
-            if keep_view {
-                let view = self.scenes[i].0.current_view();
-                self.scenes[self.i].0.set_view(view);
+            self.rebuild(self.i);
+            if self.slides[i].keep_view {
+                self.copy_view(i, self.i);
             }
             println!("Scene: {}", self.i);
         } else {
@@ -88,12 +259,12 @@ impl<'a> Deref for Presentation<'a> {
     type Target = Scene<'a>;
 
     fn deref(&self) -> &Self::Target {
-        &self.scenes[self.i].0
+        &self.slides[self.i].scene
     }
 }
 
 impl<'a> DerefMut for Presentation<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.scenes[self.i].0
+        &mut self.slides[self.i].scene
     }
 }
@@ -1,44 +1,67 @@
 use std::collections::HashMap;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use cgmath::conv::*;
+use cgmath::prelude::*;
 use cgmath::{Matrix4, Vector2};
 
-use glium::texture::{SrgbTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
-use glium::{implement_vertex, uniform, DrawParameters, IndexBuffer, Surface, VertexBuffer};
+use glium::texture::{RawImage2d, SrgbTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::{uniform, DrawParameters, IndexBuffer, Surface, VertexBuffer};
 use glium::backend::glutin::Display;
 use glium::framebuffer::SimpleFrameBuffer;
 
-#[derive(Clone, Copy)]
-struct Vertex {
-    tex_coords: [f32; 2],
+use image::RgbImage;
+
+use serde::Deserialize;
+
+use crate::backend::{check_size, output_size, Backend};
+use crate::color::Conversion;
+use crate::error::Result;
+
+mod cpu;
+
+pub use self::cpu::CpuProcessor;
+
+// The vertex implementation uses deprecated std functions internally
+#[allow(deprecated)]
+mod vertex {
+    use glium::implement_vertex;
+
+    #[derive(Clone, Copy)]
+    pub struct Vertex {
+        pub tex_coords: [f32; 2],
+    }
+
+    implement_vertex!(Vertex, tex_coords);
 }
 
-implement_vertex!(Vertex, tex_coords);
+use self::vertex::Vertex;
 
-pub struct Processor<'a> {
-    pub width: u32,
-    pub height: u32,
-    pub display: &'a Display,
+pub struct Processor {
+    /// Framebuffer size, which is also used for generated images
+    size: Cell<(u32, u32)>,
+    pub display: Display,
     vertex_buffer: VertexBuffer<Vertex>,
     index_buffer: IndexBuffer<u32>,
     shaders: RefCell<HashMap<String, glium::Program>>,
 }
 
 macro_rules! draw_with_shader {
-    ($shader_name:ident, $self:ident, $target:ident, $uniforms: expr, $draw_parameters: expr) => {
+    ($vertex_name:ident, $shader_name:ident, $self:ident, $target:ident, $uniforms: expr, $draw_parameters: expr) => {
         {
-            let key = stringify!($shader_name).to_string();
+            let key = concat!(stringify!($vertex_name), "/", stringify!($shader_name)).to_string();
             let mut shaders = $self.shaders.borrow_mut();
             let shader = if let Some(shader) = shaders.get(&key) {
                 shader
             } else {
-                let vertex_shader_src = include_str!("shaders/passthrough.vert");
+                let vertex_shader_src = include_str!(
+                    concat!("shaders/", stringify!($vertex_name), ".vert")
+                );
                 let fragment_shader_src = include_str!(
                     concat!("shaders/", stringify!($shader_name), ".frag")
                 );
                 let shader = glium::Program::from_source(
-                    $self.display,
+                    &$self.display,
                     vertex_shader_src,
                     fragment_shader_src,
                     None,
@@ -58,11 +81,52 @@ macro_rules! draw_with_shader {
                 .unwrap();
         }
     };
+    ($shader_name:ident, $self:ident, $target:ident, $uniforms: expr, $draw_parameters: expr) => {
+        draw_with_shader!(passthrough, $shader_name, $self, $target, $uniforms, $draw_parameters)
+    };
+}
+
+/// How an image is placed in the window
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Fit {
+    /// Show the whole image with black bars on the sides
+    #[default]
+    Contain,
+    /// Fill the window and crop the overflow
+    Cover,
+    /// Fill the window and ignore the aspect ratio
+    Stretch,
+    /// One image pixel per framebuffer pixel
+    OneToOne,
+}
+
+impl Fit {
+    pub fn next(self) -> Self {
+        match self {
+            Fit::Contain => Fit::Cover,
+            Fit::Cover => Fit::Stretch,
+            Fit::Stretch => Fit::OneToOne,
+            Fit::OneToOne => Fit::Contain,
+        }
+    }
+
+    /// Scale of the full screen quad that places an image of `size` into a framebuffer of `target` size
+    fn scale(self, (width, height): (u32, u32), (target_width, target_height): (u32, u32)) -> Vector2<f32> {
+        let x = width as f32 / target_width as f32;
+        let y = height as f32 / target_height as f32;
+        match self {
+            Fit::Contain => Vector2::new(x, y) / x.max(y),
+            Fit::Cover => Vector2::new(x, y) / x.min(y),
+            Fit::Stretch => Vector2::new(1.0, 1.0),
+            Fit::OneToOne => Vector2::new(x, y),
+        }
+    }
 }
 
 #[allow(dead_code)]
-impl<'a> Processor<'a> {
-    pub fn new(display: &'a Display, width: u32, height: u32) -> Self {
+impl Processor {
+    pub fn new(display: &Display) -> Self {
         let vertices = vec![
             Vertex {
                 tex_coords: [0.0, 0.0],
@@ -85,184 +149,298 @@ impl<'a> Processor<'a> {
                 .expect("Failed to create index buffer!");
 
         Self {
-            width,
-            height,
-            display,
+            size: Cell::new(display.get_framebuffer_dimensions()),
+            display: display.clone(),
             vertex_buffer,
             index_buffer,
             shaders: RefCell::new(HashMap::new()),
         }
     }
 
-    pub fn transform(&self, texture: &Texture2d, transform: Matrix4<f32>) -> Texture2d {
-        // Simple passthrough works with proper texture types
+    pub fn size(&self) -> (u32, u32) {
+        self.size.get()
+    }
+
+    /// Update the size to match the framebuffer.
+    /// Returns whether the size changed and generated images should be recreated.
+    pub fn resize(&self) -> bool {
+        let size = self.display.get_framebuffer_dimensions();
+        // Minimized windows have an empty framebuffer
+        if size == self.size.get() || size.0 == 0 || size.1 == 0 {
+            return false;
+        }
+        self.size.set(size);
+        true
+    }
+
+    pub fn srgb_to_linear(&self, texture: &SrgbTexture2d) -> Texture2d {
         let uniforms = uniform! {
             image: texture,
-            transform: array4x4(transform),
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
+        let (width, height) = texture.dimensions();
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(transform, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(visualize, self, target, &uniforms, &draw_parameters);
+        output
+    }
+
+    pub fn linear_to_srgb(&self, texture: &Texture2d) -> SrgbTexture2d {
+        let uniforms = uniform! {
+            image: texture,
+        };
+        let draw_parameters = DrawParameters {
+            ..Default::default()
+        };
+        let (width, height) = texture.dimensions();
+        let output = SrgbTexture2d::empty(
+            &self.display,
+            width,
+            height,
+        ).unwrap();
+        let mut target = SimpleFrameBuffer::new(&self.display, &output).unwrap();
+        draw_with_shader!(visualize, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn shift(&self, texture: &Texture2d, shift: Vector2<f32>) -> Texture2d {
+    /// Draw to the window. The placement is recomputed from the current framebuffer size.
+    pub fn visualize(&self, texture: &Texture2d, fit: Fit) {
+        let framebuffer = self.display.get_framebuffer_dimensions();
+        let uniforms = uniform! {
+            image: texture,
+            scale: array2(fit.scale(texture.dimensions(), framebuffer)),
+        };
+        let draw_parameters = DrawParameters {
+            ..Default::default()
+        };
+        let mut target = self.display.draw();
+        draw_with_shader!(fit, visualize, self, target, &uniforms, &draw_parameters);
+        target.finish().unwrap();
+    }
+}
+
+impl Backend for Processor {
+    type Texture = Texture2d;
+
+    fn dimensions(&self, texture: &Texture2d) -> (u32, u32) {
+        texture.dimensions()
+    }
+
+    fn transform(&self, texture: &Texture2d, transform: Matrix4<f32>) -> Texture2d {
         // Simple passthrough works with proper texture types
         let uniforms = uniform! {
             image: texture,
-            shift: array2(shift),
+            transform: array4x4(transform),
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
+        let (width, height) = texture.dimensions();
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(shift, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(transform, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn diff(&self, tex1: &Texture2d, tex2: &Texture2d, use_abs: bool) -> Texture2d {
+    fn convert(&self, texture: &Texture2d, conversion: Conversion) -> Texture2d {
         let uniforms = uniform! {
-            tex1: tex1,
-            tex2: tex2,
-            use_abs: use_abs,
+            image: texture,
+            conversion: conversion.id(),
+            params: array4(conversion.params()),
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
+        let (width, height) = texture.dimensions();
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(diff, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(convert, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn add(&self, tex1: &Texture2d, tex2: &Texture2d) -> Texture2d {
+    fn resize(&self, texture: &Texture2d, (width, height): (u32, u32)) -> Texture2d {
         let uniforms = uniform! {
-            tex1: tex1,
-            tex2: tex2,
+            image: texture,
+            transform: array4x4(Matrix4::<f32>::identity()),
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(add, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(transform, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn mul(&self, tex1: &Texture2d, tex2: &Texture2d) -> Texture2d {
+    fn shift(&self, texture: &Texture2d, shift: Vector2<f32>) -> Texture2d {
+        // Simple passthrough works with proper texture types
         let uniforms = uniform! {
-            tex1: tex1,
-            tex2: tex2,
+            image: texture,
+            shift: array2(shift),
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
+        let (width, height) = texture.dimensions();
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(mul, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(shift, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn channels(&self, r: &Texture2d, g: &Texture2d, b: &Texture2d) -> Texture2d {
+    fn diff(&self, tex1: &Texture2d, tex2: &Texture2d, use_abs: bool) -> Texture2d {
         let uniforms = uniform! {
-            r: r,
-            g: g,
-            b: b,
+            tex1: tex1,
+            tex2: tex2,
+            use_abs: use_abs,
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
+        let (width, height) = output_size(&[tex1.dimensions(), tex2.dimensions()]);
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(channels, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(diff, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn srgb_to_linear(&self, texture: &SrgbTexture2d) -> Texture2d {
+    fn add(&self, tex1: &Texture2d, tex2: &Texture2d) -> Texture2d {
         let uniforms = uniform! {
-            image: texture,
+            tex1: tex1,
+            tex2: tex2,
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
+        let (width, height) = output_size(&[tex1.dimensions(), tex2.dimensions()]);
         let output = Texture2d::empty_with_format(
-            self.display,
+            &self.display,
             UncompressedFloatFormat::F32F32F32F32,
             MipmapsOption::NoMipmap,
-            self.width,
-            self.height,
+            width,
+            height,
         ).unwrap();
         let mut target = output.as_surface();
-        draw_with_shader!(visualize, self, target, &uniforms, &draw_parameters);
+        draw_with_shader!(add, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn linear_to_srgb(&self, texture: &Texture2d) -> SrgbTexture2d {
+    fn mul(&self, tex1: &Texture2d, tex2: &Texture2d) -> Texture2d {
         let uniforms = uniform! {
-            image: texture,
+            tex1: tex1,
+            tex2: tex2,
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
-        let output = SrgbTexture2d::empty(
-            self.display,
-            self.width,
-            self.height,
+        let (width, height) = output_size(&[tex1.dimensions(), tex2.dimensions()]);
+        let output = Texture2d::empty_with_format(
+            &self.display,
+            UncompressedFloatFormat::F32F32F32F32,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
         ).unwrap();
-        let mut target = SimpleFrameBuffer::new(self.display, &output).unwrap();
-        draw_with_shader!(visualize, self, target, &uniforms, &draw_parameters);
+        let mut target = output.as_surface();
+        draw_with_shader!(mul, self, target, &uniforms, &draw_parameters);
         output
     }
 
-    pub fn visualize(&self, texture: &Texture2d) {
+    fn channels(&self, r: &Texture2d, g: &Texture2d, b: &Texture2d) -> Texture2d {
         let uniforms = uniform! {
-            image: texture,
+            r: r,
+            g: g,
+            b: b,
         };
         let draw_parameters = DrawParameters {
             ..Default::default()
         };
-        let mut target = self.display.draw();
-        draw_with_shader!(visualize, self, target, &uniforms, &draw_parameters);
-        target.finish().unwrap();
+        let (width, height) = output_size(&[r.dimensions(), g.dimensions(), b.dimensions()]);
+        let output = Texture2d::empty_with_format(
+            &self.display,
+            UncompressedFloatFormat::F32F32F32F32,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+        ).unwrap();
+        let mut target = output.as_surface();
+        draw_with_shader!(channels, self, target, &uniforms, &draw_parameters);
+        output
+    }
+
+    fn upload(&self, width: u32, height: u32, data: Vec<f32>) -> Result<Texture2d> {
+        check_size(data.len(), 3, width, height)?;
+        let tex_image = RawImage2d::from_raw_rgb(data, (width, height));
+        Ok(Texture2d::new(&self.display, tex_image)?)
+    }
+
+    fn upload_rgba(&self, width: u32, height: u32, data: &[[f32; 4]]) -> Result<Texture2d> {
+        check_size(data.len(), 1, width, height)?;
+        let data = data.iter().flatten().cloned().collect();
+        let tex_image = RawImage2d::from_raw_rgba(data, (width, height));
+        Ok(Texture2d::new(&self.display, tex_image)?)
+    }
+
+    fn upload_srgb(&self, image: &RgbImage) -> Result<Texture2d> {
+        let tex_image = RawImage2d::from_raw_rgb_reversed(image, image.dimensions());
+        let srgb = SrgbTexture2d::new(&self.display, tex_image)?;
+        Ok(self.srgb_to_linear(&srgb))
+    }
+
+    fn read(&self, texture: &Texture2d) -> Vec<[f32; 4]> {
+        // glium only offers checked reads of u8, which would clamp and quantize
+        // the values.
+        // SAFETY: `unchecked_read` is unsafe because the context may not support
+        // reading the texture as the requested pixel type, in which case the data
+        // is invalid. glium reads with glReadPixels as GL_RGBA and GL_FLOAT, sized
+        // from the texture dimensions. Every texture here is a color texture with
+        // float or normalized components, never integer ones, and OpenGL 3.0 and
+        // later convert those to GL_FLOAT. The shaders need GLSL 3.30, so a context
+        // that is too old fails when the programs are built, before any read.
+        let raw_image: RawImage2d<f32> =
+            unsafe { texture.unchecked_read::<RawImage2d<f32>, (f32, f32, f32, f32)>() };
+        raw_image
+            .data
+            .chunks(4)
+            .map(|c| [c[0], c[1], c[2], c[3]])
+            .collect()
     }
 }
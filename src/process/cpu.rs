@@ -0,0 +1,196 @@
+use cgmath::{Matrix4, Vector2, Vector4};
+
+use image::RgbImage;
+
+use crate::backend::{check_size, output_size, Backend};
+use crate::color::{srgb_decode, Conversion};
+use crate::error::Result;
+
+/// Number of texels of a `width` by `height` texture
+fn texel_count(width: u32, height: u32) -> usize {
+    width as usize * height as usize
+}
+
+/// Texture stored in main memory.
+///
+/// Rows are stored bottom to top like OpenGL textures so that texture
+/// coordinates mean the same thing on both backends.
+#[derive(Clone)]
+pub struct CpuTexture {
+    pub width: u32,
+    pub height: u32,
+    data: Vec<Vector4<f32>>,
+}
+
+impl CpuTexture {
+    pub fn new(width: u32, height: u32, data: Vec<Vector4<f32>>) -> Self {
+        assert_eq!(data.len(), texel_count(width, height), "Texture data does not match its size");
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Create a texture from tightly packed rgb data. Alpha is set to one.
+    pub fn from_raw_rgb(width: u32, height: u32, data: &[f32]) -> Self {
+        let data = data
+            .chunks(3)
+            .map(|c| Vector4::new(c[0], c[1], c[2], 1.0))
+            .collect();
+        Self::new(width, height, data)
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn data(&self) -> &[Vector4<f32>] {
+        &self.data
+    }
+
+    /// Apply `f` to every texel
+    fn map_texels<F: Fn(Vector4<f32>) -> Vector4<f32>>(&self, f: F) -> Self {
+        Self::new(self.width, self.height, self.data.iter().map(|&c| f(c)).collect())
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vector4<f32> {
+        let x = mirror(x, i64::from(self.width));
+        let y = mirror(y, i64::from(self.height));
+        self.data[(y * i64::from(self.width) + x) as usize]
+    }
+
+    /// Bilinear lookup with mirrored repeat matching the default glium sampler.
+    /// Mipmaps are not used so heavy minification may alias.
+    pub fn sample(&self, uv: Vector2<f32>) -> Vector4<f32> {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = uv.y * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let bottom = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let top = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+        bottom * (1.0 - fy) + top * fy
+    }
+}
+
+/// Index into a mirrored repeat of a texture with `size` texels
+fn mirror(i: i64, size: i64) -> i64 {
+    let m = i.rem_euclid(2 * size);
+    if m < size {
+        m
+    } else {
+        2 * size - 1 - m
+    }
+}
+
+/// Reference implementation of the `Processor` operations that runs without a GPU.
+/// Each operation computes the same result as the corresponding shader.
+pub struct CpuProcessor;
+
+impl CpuProcessor {
+    /// Evaluate `f` at the texture coordinates of every output pixel center
+    fn draw<F: Fn(Vector2<f32>) -> Vector4<f32>>(&self, (w, h): (u32, u32), f: F) -> CpuTexture {
+        let mut data = Vec::with_capacity(texel_count(w, h));
+        for y in 0..h {
+            for x in 0..w {
+                let uv = Vector2::new(
+                    (x as f32 + 0.5) / w as f32,
+                    (y as f32 + 0.5) / h as f32,
+                );
+                data.push(f(uv));
+            }
+        }
+        CpuTexture::new(w, h, data)
+    }
+
+    /// `texture` holds sRGB encoded values
+    pub fn srgb_to_linear(&self, texture: &CpuTexture) -> CpuTexture {
+        texture.map_texels(|c| {
+            Vector4::new(srgb_decode(c.x), srgb_decode(c.y), srgb_decode(c.z), c.w)
+        })
+    }
+}
+
+impl Backend for CpuProcessor {
+    type Texture = CpuTexture;
+
+    fn dimensions(&self, texture: &CpuTexture) -> (u32, u32) {
+        texture.dimensions()
+    }
+
+    fn transform(&self, texture: &CpuTexture, transform: Matrix4<f32>) -> CpuTexture {
+        self.draw(texture.dimensions(), |uv| transform * texture.sample(uv))
+    }
+
+    fn convert(&self, texture: &CpuTexture, conversion: Conversion) -> CpuTexture {
+        self.draw(texture.dimensions(), |uv| {
+            let c = texture.sample(uv);
+            conversion.apply(c.truncate()).extend(c.w)
+        })
+    }
+
+    fn resize(&self, texture: &CpuTexture, size: (u32, u32)) -> CpuTexture {
+        self.draw(size, |uv| texture.sample(uv))
+    }
+
+    fn shift(&self, texture: &CpuTexture, shift: Vector2<f32>) -> CpuTexture {
+        self.draw(texture.dimensions(), |uv| texture.sample(uv + shift))
+    }
+
+    fn diff(&self, tex1: &CpuTexture, tex2: &CpuTexture, use_abs: bool) -> CpuTexture {
+        let size = output_size(&[tex1.dimensions(), tex2.dimensions()]);
+        self.draw(size, |uv| {
+            let diff = tex1.sample(uv) - tex2.sample(uv);
+            if use_abs {
+                diff.map(f32::abs)
+            } else {
+                diff
+            }
+        })
+    }
+
+    fn add(&self, tex1: &CpuTexture, tex2: &CpuTexture) -> CpuTexture {
+        let size = output_size(&[tex1.dimensions(), tex2.dimensions()]);
+        self.draw(size, |uv| tex1.sample(uv) + tex2.sample(uv))
+    }
+
+    fn mul(&self, tex1: &CpuTexture, tex2: &CpuTexture) -> CpuTexture {
+        let size = output_size(&[tex1.dimensions(), tex2.dimensions()]);
+        self.draw(size, |uv| {
+            let c1 = tex1.sample(uv);
+            let c2 = tex2.sample(uv);
+            Vector4::new(c1.x * c2.x, c1.y * c2.y, c1.z * c2.z, c1.w * c2.w)
+        })
+    }
+
+    fn channels(&self, r: &CpuTexture, g: &CpuTexture, b: &CpuTexture) -> CpuTexture {
+        let size = output_size(&[r.dimensions(), g.dimensions(), b.dimensions()]);
+        self.draw(size, |uv| Vector4::new(r.sample(uv).x, g.sample(uv).y, b.sample(uv).z, 1.0))
+    }
+
+    fn upload(&self, width: u32, height: u32, data: Vec<f32>) -> Result<CpuTexture> {
+        check_size(data.len(), 3, width, height)?;
+        Ok(CpuTexture::from_raw_rgb(width, height, &data))
+    }
+
+    fn upload_rgba(&self, width: u32, height: u32, data: &[[f32; 4]]) -> Result<CpuTexture> {
+        check_size(data.len(), 1, width, height)?;
+        Ok(CpuTexture::new(width, height, data.iter().map(|&c| Vector4::from(c)).collect()))
+    }
+
+    fn upload_srgb(&self, image: &RgbImage) -> Result<CpuTexture> {
+        let (width, height) = image.dimensions();
+        let data: Vec<f32> = image
+            .chunks(3 * width as usize)
+            .rev()
+            .flatten()
+            .map(|&c| f32::from(c) / 255.0)
+            .collect();
+        Ok(self.srgb_to_linear(&CpuTexture::from_raw_rgb(width, height, &data)))
+    }
+
+    fn read(&self, texture: &CpuTexture) -> Vec<[f32; 4]> {
+        texture.data().iter().map(|c| [c.x, c.y, c.z, c.w]).collect()
+    }
+}
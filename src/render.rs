@@ -0,0 +1,263 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::backend::Backend;
+use crate::color::{Deficiency, Subsampling, ToneMapping, TransferFunction, YCbCrStandard, D65, SRGB};
+use crate::diagram::Diagram;
+use crate::error::{Error, Result};
+use crate::image::Image;
+use crate::output::{OutputFormat, SaveOptions};
+use crate::process::CpuProcessor;
+use crate::srgb_to_float;
+
+const USAGE: &str = "\
+Usage: color_play render [--output <dir>] [--format <format>] [--linear | --encoded]
+                         [--profile] <recipe> <image or dir>...
+
+Runs a recipe on the CPU and writes the results to the output directory
+(default: results) at the resolution of the inputs. The images are consumed in groups of the
+recipe's input count. Directories stand for the files in them in alphabetical order.
+
+Formats are png (default), png16, tiff16, exr, hdr and pfm. The float formats exr, hdr and
+pfm store linear values and the others sRGB unless --linear or --encoded says otherwise.
+--profile embeds an ICC profile of sRGB or of the linear working space that matches the values.
+Embedded profiles of the inputs are applied and unsupported ones are replaced by sRGB with a warning.";
+
+type Outputs<'a> = Vec<(&'static str, Image<'a, CpuProcessor>)>;
+
+/// Named pipeline that can be run without a display
+struct Recipe {
+    name: &'static str,
+    n_inputs: usize,
+    run: for<'a> fn(&[Image<'a, CpuProcessor>]) -> Result<Outputs<'a>>,
+}
+
+const RECIPES: &[Recipe] = &[
+    Recipe {
+        name: "mix_chroma_luma",
+        n_inputs: 2,
+        run: |images| Ok(vec![("mix_chroma_luma", mix_chroma_luma(&images[0], &images[1]))]),
+    },
+    Recipe {
+        name: "mix_lightness",
+        n_inputs: 2,
+        run: |images| Ok(vec![("mix_lightness", mix_lightness(&images[0], &images[1]))]),
+    },
+    Recipe {
+        name: "luma_random_mixes",
+        n_inputs: 1,
+        run: |images| luma_random_mixes(&images[0]),
+    },
+    Recipe {
+        name: "pink_scale",
+        n_inputs: 1,
+        run: |images| Ok(vec![("pink_scale", pink_scale(&images[0])?)]),
+    },
+    Recipe {
+        name: "chroma_subsampling",
+        n_inputs: 1,
+        run: |images| Ok(chroma_subsampling(&images[0])),
+    },
+    Recipe {
+        name: "daltonize",
+        n_inputs: 1,
+        run: |images| Ok(daltonize(&images[0])),
+    },
+    Recipe {
+        name: "tone_mapping",
+        n_inputs: 1,
+        run: |images| Ok(tone_mapping(&images[0])),
+    },
+    Recipe {
+        name: "chromaticity",
+        n_inputs: 1,
+        run: |images| Ok(vec![("chromaticity", chromaticity(&images[0])?)]),
+    },
+];
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n\n{}\n\nRecipes:", message, USAGE);
+    for recipe in RECIPES {
+        eprintln!("  {} ({} input(s))", recipe.name, recipe.n_inputs);
+    }
+    process::exit(1);
+}
+
+/// Replace directories by the files in them
+fn expand_dirs(paths: &[&String]) -> Vec<PathBuf> {
+    let mut expanded = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if !path.is_dir() {
+            expanded.push(path);
+            continue;
+        }
+        let entries = match fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("Skipping {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+        expanded.extend(files);
+    }
+    expanded
+}
+
+/// Prefix of the outputs made from `paths`, their file names without extension
+/// joined with underscores
+fn output_stem(paths: &[&Path]) -> Result<String> {
+    let stems = paths
+        .iter()
+        .map(|path| {
+            path.file_stem().map(|stem| stem.to_string_lossy()).ok_or_else(|| {
+                Error::Io(path.to_path_buf(), io::Error::new(io::ErrorKind::InvalidInput, "No file name"))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(stems.join("_"))
+}
+
+/// Entry point of the `render` subcommand. `args` excludes the subcommand itself.
+pub fn run(args: &[String]) {
+    let mut output_dir = PathBuf::from("results");
+    let mut options = SaveOptions::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match args.next() {
+                Some(dir) => output_dir = PathBuf::from(dir),
+                None => exit_with_usage("Missing output directory"),
+            },
+            "-f" | "--format" => match args.next() {
+                Some(name) => match OutputFormat::ALL.iter().find(|f| f.name() == name.as_str()) {
+                    Some(&format) => options.format = Some(format),
+                    None => exit_with_usage(&format!("Unknown format {}", name)),
+                },
+                None => exit_with_usage("Missing format"),
+            },
+            "--linear" => options.transfer = Some(TransferFunction::Linear),
+            "--encoded" => options.transfer = Some(TransferFunction::Srgb),
+            "--profile" => options.embed_profile = true,
+            "-h" | "--help" => exit_with_usage("Render recipes without a display"),
+            _ => positional.push(arg),
+        }
+    }
+    let (name, paths) = match positional.split_first() {
+        Some((name, paths)) => (name, paths),
+        None => exit_with_usage("Missing recipe"),
+    };
+    let recipe = match RECIPES.iter().find(|r| r.name == name.as_str()) {
+        Some(recipe) => recipe,
+        None => exit_with_usage(&format!("Unknown recipe {}", name)),
+    };
+    let paths = expand_dirs(paths);
+    if paths.is_empty() || !paths.len().is_multiple_of(recipe.n_inputs) {
+        exit_with_usage(&format!("{} takes {} input image(s) per run", recipe.name, recipe.n_inputs));
+    }
+
+    if let Err(err) = fs::create_dir_all(&output_dir) {
+        eprintln!("{}", Error::Io(output_dir, err));
+        process::exit(1);
+    }
+    let processor = CpuProcessor;
+    for group in paths.chunks(recipe.n_inputs) {
+        let paths: Vec<&Path> = group.iter().map(PathBuf::as_path).collect();
+        let rendered = output_stem(&paths).and_then(|stem| {
+            let images: Result<Vec<_>> = paths.iter().map(|path| Image::new(&processor, path)).collect();
+            Ok((stem, (recipe.run)(&images?)?))
+        });
+        let (stem, outputs) = match rendered {
+            Ok(rendered) => rendered,
+            Err(err) => {
+                eprintln!("Skipping {}", err);
+                continue;
+            }
+        };
+        for (suffix, image) in outputs {
+            let extension = options.format.unwrap_or(OutputFormat::Byte).extension();
+            let path = output_dir.join(format!("{}_{}.{}", stem, suffix, extension));
+            match image.save_with(&path, &options) {
+                Ok(()) => println!("Wrote {}", path.display()),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+    }
+}
+
+pub fn mix_chroma_luma<'a, B: Backend>(tex1: &Image<'a, B>, tex2: &Image<'a, B>) -> Image<'a, B> {
+    let chroma = tex1.rgb_to_xyz();
+    let luma = tex2.rgb_to_xyz();
+    Image::channels(&chroma, &luma, &chroma).xyz_to_rgb()
+}
+
+/// Colors of `tex1` with the CIE lightness of `tex2`
+pub fn mix_lightness<'a, B: Backend>(tex1: &Image<'a, B>, tex2: &Image<'a, B>) -> Image<'a, B> {
+    let chroma = tex1.rgb_to_lab(D65);
+    let lightness = tex2.rgb_to_lab(D65);
+    Image::channels(&lightness, &chroma, &chroma).lab_to_rgb(D65)
+}
+
+pub fn luma_random_mixes<'a, B: Backend>(tex: &Image<'a, B>) -> Result<Vec<(&'static str, Image<'a, B>)>> {
+    let random = Image::random(tex.backend, tex.width(), tex.height())?.rgb_to_xyz();
+    let luma = tex.rgb_to_xyz();
+    Ok(vec![
+        ("random_xy", Image::channels(&random, &random, &luma).xyz_to_rgb()),
+        ("random_yz", Image::channels(&luma, &random, &random).xyz_to_rgb()),
+        ("random_xz", Image::channels(&random, &luma, &random).xyz_to_rgb()),
+        ("random_y", Image::channels(&luma, &random, &luma).xyz_to_rgb()),
+    ])
+}
+
+pub fn pink_scale<'a, B: Backend>(tex: &Image<'a, B>) -> Result<Image<'a, B>> {
+    let pink = Image::monochrome(tex.backend, srgb_to_float(255), srgb_to_float(145), srgb_to_float(175))?;
+    let scale = tex.rgb_to_xyz().single_channel(2);
+    Ok(Image::mul(&pink, &scale))
+}
+
+pub fn chroma_subsampling<'a, B: Backend>(tex: &Image<'a, B>) -> Vec<(&'static str, Image<'a, B>)> {
+    vec![
+        ("422", tex.subsample_chroma(Subsampling::Chroma422, YCbCrStandard::Bt601)),
+        ("420", tex.subsample_chroma(Subsampling::Chroma420, YCbCrStandard::Bt601)),
+    ]
+}
+
+/// Versions of `tex` for protan, deutan and tritan viewers
+pub fn daltonize<'a, B: Backend>(tex: &Image<'a, B>) -> Vec<(&'static str, Image<'a, B>)> {
+    vec![
+        ("daltonize_protan", tex.daltonize(Deficiency::Protan, 1.0)),
+        ("daltonize_deutan", tex.daltonize(Deficiency::Deutan, 1.0)),
+        ("daltonize_tritan", tex.daltonize(Deficiency::Tritan, 1.0)),
+    ]
+}
+
+/// Chromaticities of the pixels of `tex` in the sRGB triangle
+pub fn chromaticity<'a, B: Backend>(tex: &Image<'a, B>) -> Result<Image<'a, B>> {
+    let diagram = Diagram {
+        spaces: vec![SRGB],
+        ..Diagram::default()
+    };
+    Image::chromaticity_diagram(tex.backend, tex.width(), tex.height(), &diagram, Some(tex))
+}
+
+/// `tex` compressed by each tone mapping operator with its default white point
+pub fn tone_mapping<'a, B: Backend>(tex: &Image<'a, B>) -> Vec<(&'static str, Image<'a, B>)> {
+    [
+        ("reinhard", ToneMapping::Reinhard),
+        ("reinhard_local", ToneMapping::ReinhardLocal),
+        ("aces", ToneMapping::Aces),
+        ("hable", ToneMapping::Hable),
+        ("agx", ToneMapping::Agx),
+    ]
+    .iter()
+    .map(|&(name, mapping)| (name, tex.tone_map(mapping, 0.0, mapping.default_white())))
+    .collect()
+}
@@ -1,22 +1,76 @@
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
 
 use cgmath::Vector3;
 
+use serde::Deserialize;
+
+use crate::color::{AdaptationMethod, CvdModel, Range, Spectrum, ToneMapping, YCbCrStandard};
+use crate::error::Result;
 use crate::image::Image;
 use crate::process::Processor;
 
 mod channels;
 mod combination;
+mod deficiency;
+mod exposure;
+mod gamut;
+mod hue;
+mod illuminants;
+mod metamers;
 mod movement;
 mod permutation;
 mod plain;
+mod spaces;
+mod subsampling;
 
 use self::channels::Channels;
 use self::combination::Combination;
+use self::deficiency::Deficiencies;
+use self::exposure::Exposure;
+use self::gamut::Gamut;
+use self::hue::Hue;
+use self::illuminants::Illuminants;
+use self::metamers::Metamers;
 use self::movement::Movement;
 use self::permutation::Permutation;
 use self::plain::Plain;
+use self::spaces::Spaces;
+use self::subsampling::Subsampling;
+
+/// Color space in which a scene shuffles channels
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Space {
+    #[default]
+    Rgb,
+    Oklab,
+    /// BT.709 Y'CbCr with luma in the first channel
+    Ycbcr,
+    /// YCoCg with luma in the first channel
+    Ycocg,
+}
+
+impl Space {
+    /// Convert from linear rgb to the space
+    pub fn forward<'a>(self, image: &Image<'a>) -> Image<'a> {
+        match self {
+            Space::Rgb => image.clone(),
+            Space::Oklab => image.rgb_to_oklab(),
+            Space::Ycbcr => image.rgb_to_ycbcr(YCbCrStandard::Bt709, Range::Full),
+            Space::Ycocg => image.rgb_to_ycocg(),
+        }
+    }
+
+    /// Convert from the space back to linear rgb
+    pub fn inverse<'a>(self, image: &Image<'a>) -> Image<'a> {
+        match self {
+            Space::Rgb => image.clone(),
+            Space::Oklab => image.oklab_to_rgb(),
+            Space::Ycbcr => image.ycbcr_to_rgb(YCbCrStandard::Bt709, Range::Full),
+            Space::Ycocg => image.ycocg_to_rgb(),
+        }
+    }
+}
 
 pub trait ViewChange {
     fn current_view(&self) -> usize;
@@ -27,7 +81,7 @@ pub trait ViewChange {
 }
 
 pub trait SceneT: ViewChange {
-    fn image(&self) -> Image;
+    fn image(&self) -> Image<'_>;
 
     fn toggle(&mut self);
 
@@ -51,31 +105,86 @@ pub trait SceneT: ViewChange {
 pub enum Scene<'a> {
     Channels(Channels<'a>),
     Combination(Combination<'a>),
+    Deficiencies(Deficiencies<'a>),
+    Exposure(Exposure<'a>),
+    Gamut(Gamut<'a>),
+    Hue(Hue<'a>),
+    Illuminants(Illuminants<'a>),
+    Metamers(Metamers<'a>),
     Movement(Movement<'a>),
     Permutation(Permutation<'a>),
     Plain(Plain<'a>),
+    Spaces(Spaces<'a>),
+    Subsampling(Subsampling<'a>),
 }
 
 impl<'a> Scene<'a> {
-    pub fn channels(images: [Image<'a>; 3]) -> Self {
-        Scene::Channels(Channels::new(images))
+    pub fn channels(images: [Image<'a>; 3], space: Space) -> Self {
+        Scene::Channels(Channels::new(images, space))
     }
 
     pub fn combination(n: usize, image1: Image<'a>, image2: Image<'a>) -> Self {
         Scene::Combination(Combination::new(n, image1, image2))
     }
 
-    pub fn movement(processor: &'a Processor, dir: &Path) -> Self {
-        Scene::Movement(Movement::new(processor, dir))
+    pub fn deficiencies(image: Image<'a>, model: CvdModel, severity: f32) -> Self {
+        Scene::Deficiencies(Deficiencies::new(image, model, severity))
+    }
+
+    pub fn exposure(image: Image<'a>, mapping: ToneMapping, white: f32, stops: usize) -> Self {
+        Scene::Exposure(Exposure::new(image, mapping, white, stops))
+    }
+
+    pub fn gamut(image: Image<'a>, chroma: f32, warning: Vector3<f32>) -> Self {
+        Scene::Gamut(Gamut::new(image, chroma, warning))
     }
 
-    pub fn permutation(images: Vec<Image<'a>>, permutation: Vector3<usize>) -> Self {
-        Scene::Permutation(Permutation::new(images, permutation))
+    pub fn hue(n: usize, image: Image<'a>) -> Self {
+        Scene::Hue(Hue::new(n, image))
+    }
+
+    pub fn illuminants(image: Image<'a>, method: AdaptationMethod) -> Self {
+        Scene::Illuminants(Illuminants::new(image, method))
+    }
+
+    pub fn illuminant_metamerism(
+        processor: &'a Processor,
+        width: u32,
+        height: u32,
+        matched: Spectrum,
+        other: Spectrum,
+    ) -> Result<Self> {
+        Ok(Scene::Metamers(Metamers::illuminant(processor, width, height, matched, other)?))
+    }
+
+    pub fn observer_metamerism(
+        processor: &'a Processor,
+        width: u32,
+        height: u32,
+        light: Spectrum,
+    ) -> Result<Self> {
+        Ok(Scene::Metamers(Metamers::observer(processor, width, height, light)?))
+    }
+
+    pub fn movement(mask: Image<'a>) -> Result<Self> {
+        Ok(Scene::Movement(Movement::new(mask)?))
+    }
+
+    pub fn permutation(images: Vec<Image<'a>>, permutation: Vector3<usize>, space: Space) -> Self {
+        Scene::Permutation(Permutation::new(images, permutation, space))
     }
 
     pub fn plain(image: Image<'a>) -> Self {
         Scene::Plain(Plain::new(image))
     }
+
+    pub fn spaces(image: Image<'a>) -> Self {
+        Scene::Spaces(Spaces::new(image))
+    }
+
+    pub fn subsampling(image: Image<'a>, standard: YCbCrStandard) -> Self {
+        Scene::Subsampling(Subsampling::new(image, standard))
+    }
 }
 
 impl<'a> Deref for Scene<'a> {
@@ -85,9 +194,17 @@ impl<'a> Deref for Scene<'a> {
         match self {
             Scene::Channels(inner) => inner,
             Scene::Combination(inner) => inner,
+            Scene::Deficiencies(inner) => inner,
+            Scene::Exposure(inner) => inner,
+            Scene::Gamut(inner) => inner,
+            Scene::Hue(inner) => inner,
+            Scene::Illuminants(inner) => inner,
+            Scene::Metamers(inner) => inner,
             Scene::Movement(inner) => inner,
             Scene::Permutation(inner) => inner,
             Scene::Plain(inner) => inner,
+            Scene::Spaces(inner) => inner,
+            Scene::Subsampling(inner) => inner,
         }
     }
 }
@@ -97,9 +214,17 @@ impl<'a> DerefMut for Scene<'a> {
         match self {
             Scene::Channels(inner) => inner,
             Scene::Combination(inner) => inner,
+            Scene::Deficiencies(inner) => inner,
+            Scene::Exposure(inner) => inner,
+            Scene::Gamut(inner) => inner,
+            Scene::Hue(inner) => inner,
+            Scene::Illuminants(inner) => inner,
+            Scene::Metamers(inner) => inner,
             Scene::Movement(inner) => inner,
             Scene::Permutation(inner) => inner,
             Scene::Plain(inner) => inner,
+            Scene::Spaces(inner) => inner,
+            Scene::Subsampling(inner) => inner,
         }
     }
 }
@@ -1,17 +1,24 @@
 use crate::image::Image;
 
-use super::{SceneT, ViewChange};
+use super::{SceneT, Space, ViewChange};
 
 pub struct Channels<'a> {
     i: usize,
+    /// Images converted to `space`
     images: [Image<'a>; 3],
+    space: Space,
 }
 
 impl<'a> Channels<'a> {
-    pub fn new(images: [Image<'a>; 3]) -> Self {
+    pub fn new(images: [Image<'a>; 3], space: Space) -> Self {
         Self {
             i: 0,
-            images,
+            images: [
+                space.forward(&images[0]),
+                space.forward(&images[1]),
+                space.forward(&images[2]),
+            ],
+            space,
         }
     }
 }
@@ -33,7 +40,7 @@ impl ViewChange for Channels<'_> {
 impl SceneT for Channels<'_> {
     fn toggle(&mut self) {}
 
-    fn image(&self) -> Image {
+    fn image(&self) -> Image<'_> {
         let (r, g, b) = match self.i {
             0 => (&self.images[1], &self.images[0], &self.images[2]),
             1 => (&self.images[2], &self.images[0], &self.images[1]),
@@ -43,9 +50,9 @@ impl SceneT for Channels<'_> {
             5 => (&self.images[1], &self.images[2], &self.images[0]),
             _ => {
                 println!("Invalid channel permutation");
-                return self.images[0].uscale(1.0)
+                return self.space.inverse(&self.images[0])
             }
         };
-        Image::channels(r, g, b)
+        self.space.inverse(&Image::channels(r, g, b))
     }
 }
@@ -37,7 +37,7 @@ impl ViewChange for Combination<'_> {
 impl SceneT for Combination<'_> {
     fn toggle(&mut self) {}
 
-    fn image(&self) -> Image {
+    fn image(&self) -> Image<'_> {
         let scale = (self.i as f32 / (self.n - 1) as f32).min(0.995);
         Image::add(&self.image1.uscale(1.0 - scale), &self.image2.uscale(scale))
     }
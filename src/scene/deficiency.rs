@@ -0,0 +1,57 @@
+use crate::color::{CvdModel, Deficiency};
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+/// Steps from normal vision through the simulated color vision deficiencies
+pub struct Deficiencies<'a> {
+    i: usize,
+    /// Normal vision followed by `Deficiency::ALL`
+    images: Vec<Image<'a>>,
+    original: bool,
+}
+
+impl<'a> Deficiencies<'a> {
+    pub fn new(image: Image<'a>, model: CvdModel, severity: f32) -> Self {
+        let mut images = vec![image.clone()];
+        images.extend(
+            Deficiency::ALL
+                .iter()
+                .map(|&deficiency| image.simulate_cvd(deficiency, model, severity)),
+        );
+        Self {
+            i: 0,
+            images,
+            original: false,
+        }
+    }
+}
+
+impl ViewChange for Deficiencies<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        self.images.len()
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Deficiencies<'_> {
+    /// Switch between the simulated and normal vision
+    fn toggle(&mut self) {
+        self.original = !self.original;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.original {
+            self.images[0].clone()
+        } else {
+            self.images[self.i].clone()
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::color::ToneMapping;
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+/// Steps through exposures of a high dynamic range image one stop apart, from
+/// `-stops` to `stops` with the unchanged image in the middle
+pub struct Exposure<'a> {
+    i: usize,
+    stops: usize,
+    image: Image<'a>,
+    mapping: ToneMapping,
+    white: f32,
+    tone_mapped: bool,
+    /// `image` at the current exposure, mapped or clipped. Redraws show it as it
+    /// is and only view changes and toggles compute it again.
+    shown: Image<'a>,
+}
+
+impl<'a> Exposure<'a> {
+    pub fn new(image: Image<'a>, mapping: ToneMapping, white: f32, stops: usize) -> Self {
+        let shown = image.clone();
+        let mut exposure = Self {
+            i: stops,
+            stops,
+            image,
+            mapping,
+            white,
+            tone_mapped: true,
+            shown,
+        };
+        exposure.update();
+        exposure
+    }
+
+    fn update(&mut self) {
+        let stops = self.i as f32 - self.stops as f32;
+        self.shown = if self.tone_mapped {
+            self.image.tone_map(self.mapping, stops, self.white)
+        } else {
+            self.image.expose(stops)
+        };
+    }
+}
+
+impl ViewChange for Exposure<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        2 * self.stops + 1
+    }
+
+    fn set_view(&mut self, i: usize) {
+        if i != self.i {
+            self.i = i;
+            self.update();
+        }
+    }
+}
+
+impl SceneT for Exposure<'_> {
+    /// Switch between tone mapping and clipping by the display
+    fn toggle(&mut self) {
+        self.tone_mapped = !self.tone_mapped;
+        self.update();
+    }
+
+    fn image(&self) -> Image<'_> {
+        self.shown.clone()
+    }
+}
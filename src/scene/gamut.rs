@@ -0,0 +1,68 @@
+use cgmath::Vector3;
+
+use crate::color::{ChromaSpace, GamutMapping};
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+const MAPPINGS: [GamutMapping; 5] = [
+    GamutMapping::Clamp,
+    GamutMapping::Clip,
+    GamutMapping::ReduceChroma(ChromaSpace::Lch),
+    GamutMapping::ReduceChroma(ChromaSpace::Oklch),
+    GamutMapping::SoftKnee(0.8),
+];
+
+/// Boosts the OKLCh chroma of an image past the displayable colors. The first view marks
+/// the undisplayable pixels and the others step through the gamut mappings.
+pub struct Gamut<'a> {
+    i: usize,
+    /// Gamut warning followed by the image mapped with each of `MAPPINGS`
+    images: Vec<Image<'a>>,
+    /// Shown without mapping, so clamped by the display
+    unmapped: Image<'a>,
+    show_unmapped: bool,
+}
+
+impl<'a> Gamut<'a> {
+    pub fn new(image: Image<'a>, chroma: f32, warning: Vector3<f32>) -> Self {
+        let boosted = image.rgb_to_oklch().scale(1.0, chroma, 1.0).oklch_to_rgb();
+        let mut images = vec![boosted.gamut_warning(warning)];
+        images.extend(MAPPINGS.iter().map(|&mapping| boosted.gamut_map(mapping)));
+        Self {
+            i: 0,
+            images,
+            unmapped: boosted,
+            show_unmapped: false,
+        }
+    }
+}
+
+impl ViewChange for Gamut<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        self.images.len()
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Gamut<'_> {
+    /// Switch between the view and the unmapped image
+    fn toggle(&mut self) {
+        self.show_unmapped = !self.show_unmapped;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.show_unmapped {
+            self.unmapped.clone()
+        } else {
+            self.images[self.i].clone()
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+/// Rotates the hue of an image a full turn over the views
+pub struct Hue<'a> {
+    i: usize,
+    n: usize,
+    image: Image<'a>,
+    original: bool,
+}
+
+impl<'a> Hue<'a> {
+    pub fn new(n: usize, image: Image<'a>) -> Self {
+        Self {
+            i: 0,
+            n,
+            image,
+            original: false,
+        }
+    }
+}
+
+impl ViewChange for Hue<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        self.n
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Hue<'_> {
+    /// Switch between the rotated and the original image
+    fn toggle(&mut self) {
+        self.original = !self.original;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.original {
+            return self.image.clone();
+        }
+        let degrees = 360.0 * self.i as f32 / self.n as f32;
+        self.image.hue_rotate(degrees)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::color::{AdaptationMethod, Illuminant, D65};
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+/// Shows an sRGB image as if it was lit by each of `Illuminant::ALL`
+pub struct Illuminants<'a> {
+    i: usize,
+    images: Vec<Image<'a>>,
+    original: Image<'a>,
+    show_original: bool,
+}
+
+impl<'a> Illuminants<'a> {
+    pub fn new(image: Image<'a>, method: AdaptationMethod) -> Self {
+        let images = Illuminant::ALL
+            .iter()
+            .map(|illuminant| image.chromatic_adapt(D65, illuminant.white(), method))
+            .collect();
+        Self {
+            i: 0,
+            images,
+            original: image,
+            show_original: false,
+        }
+    }
+}
+
+impl ViewChange for Illuminants<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        self.images.len()
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Illuminants<'_> {
+    /// Switch between the illuminant and the D65 original
+    fn toggle(&mut self) {
+        self.show_original = !self.show_original;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.show_original {
+            self.original.clone()
+        } else {
+            self.images[self.i].clone()
+        }
+    }
+}
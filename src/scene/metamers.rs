@@ -0,0 +1,164 @@
+use std::f32::consts::PI;
+
+use cgmath::prelude::*;
+
+use crate::color::{Conversion, Observer, Spectrum};
+use crate::diagram::Swatches;
+use crate::error::Result;
+use crate::image::Image;
+use crate::process::Processor;
+
+use super::{SceneT, ViewChange};
+
+/// Smooth reflectances of a gray, a red, a green, a blue and a yellow surface
+fn reflectances() -> Vec<Spectrum> {
+    vec![
+        Spectrum::constant(0.4),
+        Spectrum::sigmoid(590.0, 15.0) * 0.6 + Spectrum::constant(0.08),
+        Spectrum::gaussian(530.0, 35.0, 0.45) + Spectrum::constant(0.08),
+        Spectrum::gaussian(450.0, 30.0, 0.5) + Spectrum::constant(0.08),
+        Spectrum::sigmoid(520.0, 12.0) * 0.7 + Spectrum::constant(0.05),
+    ]
+}
+
+/// Wiggles that become different metamers once their visible part is removed
+fn perturbations() -> Vec<Spectrum> {
+    [(3.0, 0.0), (5.0, 0.5), (7.0, 0.25)]
+        .iter()
+        .map(|&(cycles, phase)| {
+            Spectrum::from_fn(|nm| (2.0 * PI * (cycles * (nm - 380.0) / 400.0 + phase)).sin())
+        })
+        .collect()
+}
+
+/// Metamer of `reflectance` under `matched` that looks as different as possible
+/// under `other`. The candidates shift the response to one of the color matching
+/// functions under `other` up or down.
+fn metameric_pair(reflectance: &Spectrum, matched: &Swatches, other: &Swatches) -> Spectrum {
+    let oklab = |swatches: &Swatches, reflectance: &Spectrum| {
+        Conversion::RgbToOklab.apply(swatches.color(reflectance))
+    };
+    let original = oklab(other, reflectance);
+    (0..3)
+        .flat_map(|channel| {
+            let perturbation = Spectrum::from_fn(|nm| other.observer.cmf(nm)[channel]) * other.illuminant;
+            vec![perturbation, perturbation * -1.0]
+        })
+        .map(|perturbation| reflectance.metamer(&matched.illuminant, matched.observer, &perturbation))
+        .map(|metamer| ((oklab(other, &metamer) - original).magnitude(), metamer))
+        .fold((0.0, *reflectance), |best, candidate| if candidate.0 > best.0 { candidate } else { best })
+        .1
+}
+
+/// Each view shows a set of reflectances that have the same color under one
+/// viewing condition. Toggling switches to another condition under which they
+/// no longer match.
+pub struct Metamers<'a> {
+    i: usize,
+    images: Vec<Image<'a>>,
+    /// The same sets under the other viewing condition
+    others: Vec<Image<'a>>,
+    show_other: bool,
+}
+
+impl<'a> Metamers<'a> {
+    /// Observer metamerism: a reflectance next to metamers of it for the CIE 1931
+    /// observer under `light`, and the same surfaces seen by the CIE 1964 observer
+    pub fn observer(processor: &'a Processor, width: u32, height: u32, light: Spectrum) -> Result<Self> {
+        let matched = Swatches {
+            observer: Observer::Cie1931,
+            illuminant: light,
+            curves: true,
+        };
+        let other = Swatches {
+            observer: Observer::Cie1964,
+            ..matched.clone()
+        };
+        let perturbations = perturbations();
+        let sets: Vec<Vec<_>> = reflectances()
+            .iter()
+            .map(|reflectance| {
+                let metamers = perturbations
+                    .iter()
+                    .map(|perturbation| reflectance.metamer(&light, matched.observer, perturbation));
+                Some(*reflectance).into_iter().chain(metamers).collect()
+            })
+            .collect();
+        Self::new(processor, width, height, &sets, &matched, &other)
+    }
+
+    /// Illuminant metamerism: pairs of reflectances that match under `matched`,
+    /// and the same pairs lit by `other`
+    pub fn illuminant(
+        processor: &'a Processor,
+        width: u32,
+        height: u32,
+        matched: Spectrum,
+        other: Spectrum,
+    ) -> Result<Self> {
+        let matched = Swatches {
+            observer: Observer::Cie1931,
+            illuminant: matched,
+            curves: true,
+        };
+        let other = Swatches {
+            illuminant: other,
+            ..matched.clone()
+        };
+        let pairs: Vec<Vec<_>> = reflectances()
+            .iter()
+            .map(|reflectance| vec![*reflectance, metameric_pair(reflectance, &matched, &other)])
+            .collect();
+        Self::new(processor, width, height, &pairs, &matched, &other)
+    }
+
+    fn new(
+        processor: &'a Processor,
+        width: u32,
+        height: u32,
+        sets: &[Vec<Spectrum>],
+        matched: &Swatches,
+        other: &Swatches,
+    ) -> Result<Self> {
+        let render = |swatches: &Swatches| {
+            sets.iter()
+                .map(|set| Image::swatches(processor, width, height, swatches, set))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            i: 0,
+            images: render(matched)?,
+            others: render(other)?,
+            show_other: false,
+        })
+    }
+}
+
+impl ViewChange for Metamers<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        self.images.len()
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Metamers<'_> {
+    /// Switch between the viewing condition under which the sets match and the other one
+    fn toggle(&mut self) {
+        self.show_other = !self.show_other;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.show_other {
+            self.others[self.i].clone()
+        } else {
+            self.images[self.i].clone()
+        }
+    }
+}
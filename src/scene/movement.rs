@@ -1,7 +1,5 @@
-use std::path::Path;
-
+use crate::error::Result;
 use crate::image::Image;
-use crate::process::Processor;
 
 use super::{SceneT, ViewChange};
 
@@ -13,16 +11,15 @@ pub struct Movement<'a> {
 }
 
 impl<'a> Movement<'a> {
-    pub fn new(processor: &'a Processor, dir: &Path) -> Self {
-        let mask = Image::new(&processor, &dir.join("pikachu.jpg"));
-        let neg_mask = Image::diff(&mask, &Image::grayscale(&processor, 1.0), true);
-        let background = Image::random(&processor);
-        Self {
+    pub fn new(mask: Image<'a>) -> Result<Self> {
+        let neg_mask = Image::diff(&mask, &Image::grayscale(mask.backend, 1.0)?, true);
+        let background = Image::random(mask.backend, mask.width(), mask.height())?;
+        Ok(Self {
             background,
             mask,
             neg_mask,
             shift: false,
-        }
+        })
     }
 }
 
@@ -44,7 +41,7 @@ impl SceneT for Movement<'_> {
         self.shift = !self.shift;
     }
 
-    fn image(&self) -> Image {
+    fn image(&self) -> Image<'_> {
         let (dx, dy) = if self.shift {
             rand::random::<(f32, f32)>()
         } else {
@@ -2,20 +2,23 @@ use cgmath::Vector3;
 
 use crate::image::Image;
 
-use super::{SceneT, ViewChange};
+use super::{SceneT, Space, ViewChange};
 
 pub struct Permutation<'a> {
     i: usize,
+    /// Images converted to `space`
     views: Vec<Image<'a>>,
     permutation: Vector3<usize>,
+    space: Space,
 }
 
 impl<'a> Permutation<'a> {
-    pub fn new(images: Vec<Image<'a>>, permutation: Vector3<usize>) -> Self {
+    pub fn new(images: Vec<Image<'a>>, permutation: Vector3<usize>, space: Space) -> Self {
         Self {
             i: 0,
-            views: images,
+            views: images.iter().map(|image| space.forward(image)).collect(),
             permutation,
+            space,
         }
     }
 }
@@ -37,12 +40,13 @@ impl ViewChange for Permutation<'_> {
 impl SceneT for Permutation<'_> {
     fn toggle(&mut self) {}
 
-    fn image(&self) -> Image {
+    fn image(&self) -> Image<'_> {
         let tex = &self.views[self.i];
-        tex.permute(
+        let permuted = tex.permute(
             self.permutation.x,
             self.permutation.y,
             self.permutation.z
-        )
+        );
+        self.space.inverse(&permuted)
     }
 }
@@ -29,7 +29,7 @@ impl ViewChange for Plain<'_> {
 impl SceneT for Plain<'_> {
     fn toggle(&mut self) {}
 
-    fn image(&self) -> Image {
+    fn image(&self) -> Image<'_> {
         self.image.uscale(1.0)
     }
 }
@@ -0,0 +1,60 @@
+use crate::color::{RgbColorSpace, SRGB};
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+/// Shows an sRGB image converted to each of `RgbColorSpace::ALL` and displayed without
+/// color management, which is how a wide gamut file looks in a viewer that assumes sRGB
+pub struct Spaces<'a> {
+    i: usize,
+    images: Vec<Image<'a>>,
+    original: bool,
+}
+
+impl<'a> Spaces<'a> {
+    pub fn new(image: Image<'a>) -> Self {
+        let images = RgbColorSpace::ALL
+            .iter()
+            .map(|space| {
+                image
+                    .convert_rgb_space(&SRGB, space)
+                    .encode(space.transfer)
+                    .decode(SRGB.transfer)
+            })
+            .collect();
+        Self {
+            i: 0,
+            images,
+            original: false,
+        }
+    }
+}
+
+impl ViewChange for Spaces<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        self.images.len()
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Spaces<'_> {
+    /// Switch between the unmanaged and the sRGB original
+    fn toggle(&mut self) {
+        self.original = !self.original;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.original {
+            self.images[0].clone()
+        } else {
+            self.images[self.i].clone()
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::color::{self, YCbCrStandard};
+use crate::image::Image;
+
+use super::{SceneT, ViewChange};
+
+const SUBSAMPLINGS: [color::Subsampling; 3] = [
+    color::Subsampling::Chroma444,
+    color::Subsampling::Chroma422,
+    color::Subsampling::Chroma420,
+];
+
+/// Shows the artifacts of chroma subsampling
+pub struct Subsampling<'a> {
+    i: usize,
+    images: Vec<Image<'a>>,
+    original: bool,
+}
+
+impl<'a> Subsampling<'a> {
+    pub fn new(image: Image<'a>, standard: YCbCrStandard) -> Self {
+        let images = SUBSAMPLINGS
+            .iter()
+            .map(|&subsampling| image.subsample_chroma(subsampling, standard))
+            .collect();
+        Self {
+            i: 0,
+            images,
+            original: false,
+        }
+    }
+}
+
+impl ViewChange for Subsampling<'_> {
+    fn current_view(&self) -> usize {
+        self.i
+    }
+
+    fn n_views(&self) -> usize {
+        SUBSAMPLINGS.len()
+    }
+
+    fn set_view(&mut self, i: usize) {
+        self.i = i;
+    }
+}
+
+impl SceneT for Subsampling<'_> {
+    /// Switch between the subsampled and the full resolution chroma
+    fn toggle(&mut self) {
+        self.original = !self.original;
+    }
+
+    fn image(&self) -> Image<'_> {
+        if self.original {
+            self.images[0].clone()
+        } else {
+            self.images[self.i].clone()
+        }
+    }
+}
@@ -0,0 +1,496 @@
+#version 330
+
+in vec2 v_tex_coords;
+
+out vec4 color;
+
+uniform sampler2D image;
+// Conversion::id
+uniform int conversion;
+// Conversion::params
+uniform vec4 params;
+
+const float LAB_DELTA = 6.0 / 29.0;
+
+float lab_f(float t) {
+    if (t > LAB_DELTA * LAB_DELTA * LAB_DELTA) {
+        return pow(t, 1.0 / 3.0);
+    } else {
+        return t / (3.0 * LAB_DELTA * LAB_DELTA) + 4.0 / 29.0;
+    }
+}
+
+float lab_f_inv(float t) {
+    if (t > LAB_DELTA) {
+        return t * t * t;
+    } else {
+        return 3.0 * LAB_DELTA * LAB_DELTA * (t - 4.0 / 29.0);
+    }
+}
+
+vec3 xyz_to_lab(vec3 xyz, vec3 white) {
+    float fx = lab_f(xyz.x / white.x);
+    float fy = lab_f(xyz.y / white.y);
+    float fz = lab_f(xyz.z / white.z);
+    return vec3(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz));
+}
+
+vec3 lab_to_xyz(vec3 lab, vec3 white) {
+    float fy = (lab.x + 16.0) / 116.0;
+    float fx = fy + lab.y / 500.0;
+    float fz = fy - lab.z / 200.0;
+    return white * vec3(lab_f_inv(fx), lab_f_inv(fy), lab_f_inv(fz));
+}
+
+vec3 lab_to_lch(vec3 lab) {
+    // atan is undefined at the origin
+    float h = lab.yz == vec2(0.0) ? 0.0 : degrees(atan(lab.z, lab.y));
+    if (h < 0.0) {
+        h += 360.0;
+    }
+    return vec3(lab.x, length(lab.yz), h);
+}
+
+vec3 lch_to_lab(vec3 lch) {
+    float h = radians(lch.z);
+    return vec3(lch.x, lch.y * cos(h), lch.y * sin(h));
+}
+
+vec3 rgb_to_oklab(vec3 rgb) {
+    mat3 to_lms = transpose(mat3(
+        0.4122214708, 0.5363325363, 0.0514459929,
+        0.2119034982, 0.6806995451, 0.1073969566,
+        0.0883024619, 0.2817188376, 0.6299787005
+    ));
+    mat3 to_lab = transpose(mat3(
+        0.2104542553, 0.7936177850, -0.0040720468,
+        1.9779984951, -2.4285922050, 0.4505937099,
+        0.0259040371, 0.7827717662, -0.8086757660
+    ));
+    vec3 lms = to_lms * rgb;
+    return to_lab * (sign(lms) * pow(abs(lms), vec3(1.0 / 3.0)));
+}
+
+vec3 oklab_to_rgb(vec3 lab) {
+    mat3 to_lms = transpose(mat3(
+        1.0, 0.3963377774, 0.2158037573,
+        1.0, -0.1055613458, -0.0638541728,
+        1.0, -0.0894841775, -1.2914855480
+    ));
+    mat3 to_rgb = transpose(mat3(
+        4.0767416621, -3.3077115913, 0.2309699292,
+        -1.2684380046, 2.6097574011, -0.3413193965,
+        -0.0041960863, -0.7034186147, 1.7076147010
+    ));
+    vec3 lms = to_lms * lab;
+    return to_rgb * (lms * lms * lms);
+}
+
+float max_component(vec3 c) {
+    return max(c.r, max(c.g, c.b));
+}
+
+float min_component(vec3 c) {
+    return min(c.r, min(c.g, c.b));
+}
+
+float hexagonal_hue(vec3 rgb) {
+    float max_c = max_component(rgb);
+    float d = max_c - min_component(rgb);
+    if (d == 0.0) {
+        return 0.0;
+    } else if (max_c == rgb.r) {
+        float h = (rgb.g - rgb.b) / d;
+        return 60.0 * (h - 6.0 * floor(h / 6.0));
+    } else if (max_c == rgb.g) {
+        return 60.0 * ((rgb.b - rgb.r) / d + 2.0);
+    } else {
+        return 60.0 * ((rgb.r - rgb.g) / d + 4.0);
+    }
+}
+
+vec3 rgb_to_hsv(vec3 rgb) {
+    float max_c = max_component(rgb);
+    float d = max_c - min_component(rgb);
+    float s = max_c > 0.0 ? d / max_c : 0.0;
+    return vec3(hexagonal_hue(rgb), s, max_c);
+}
+
+vec3 hsv_to_rgb(vec3 hsv) {
+    float s = clamp(hsv.y, 0.0, 1.0);
+    vec3 k = mod(vec3(5.0, 3.0, 1.0) + hsv.x / 60.0, 6.0);
+    return hsv.z - hsv.z * s * clamp(min(k, 4.0 - k), 0.0, 1.0);
+}
+
+vec3 rgb_to_hsl(vec3 rgb) {
+    float max_c = max_component(rgb);
+    float min_c = min_component(rgb);
+    float l = (max_c + min_c) / 2.0;
+    float s = l > 0.0 && l < 1.0 ? (max_c - min_c) / (1.0 - abs(2.0 * l - 1.0)) : 0.0;
+    return vec3(hexagonal_hue(rgb), s, l);
+}
+
+vec3 hsl_to_rgb(vec3 hsl) {
+    float a = clamp(hsl.y, 0.0, 1.0) * min(hsl.z, 1.0 - hsl.z);
+    vec3 k = mod(vec3(0.0, 8.0, 4.0) + hsl.x / 30.0, 12.0);
+    return hsl.z - a * clamp(min(k - 3.0, 9.0 - k), -1.0, 1.0);
+}
+
+vec3 rgb_to_hsi(vec3 rgb) {
+    float i = (rgb.r + rgb.g + rgb.b) / 3.0;
+    float s = i > 0.0 ? 1.0 - min_component(rgb) / i : 0.0;
+    float num = 0.5 * ((rgb.r - rgb.g) + (rgb.r - rgb.b));
+    float den = sqrt((rgb.r - rgb.g) * (rgb.r - rgb.g) + (rgb.r - rgb.b) * (rgb.g - rgb.b));
+    float h = 0.0;
+    if (den > 0.0) {
+        h = degrees(acos(clamp(num / den, -1.0, 1.0)));
+        if (rgb.b > rgb.g) {
+            h = 360.0 - h;
+        }
+    }
+    return vec3(h, s, i);
+}
+
+vec3 hsi_to_rgb(vec3 hsi) {
+    float h = mod(hsi.x, 360.0);
+    float s = clamp(hsi.y, 0.0, 1.0);
+    float i = hsi.z;
+    float sector = min(floor(h / 120.0), 2.0);
+    h = radians(h - 120.0 * sector);
+    float low = i * (1.0 - s);
+    float high = i * (1.0 + s * cos(h) / cos(radians(60.0) - h));
+    float rest = 3.0 * i - low - high;
+    if (sector == 0.0) {
+        return vec3(high, rest, low);
+    } else if (sector == 1.0) {
+        return vec3(low, high, rest);
+    } else {
+        return vec3(rest, low, high);
+    }
+}
+
+float srgb_encode(float c) {
+    return c <= 0.0031308 ? 12.92 * c : 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+float srgb_decode(float c) {
+    return c <= 0.04045 ? c / 12.92 : pow((c + 0.055) / 1.055, 2.4);
+}
+
+const float BT1886_GAMMA = 2.4;
+
+// Gain and black lift of the BT.1886 EOTF for white at 1
+vec2 bt1886_constants(float black) {
+    black = pow(black, 1.0 / BT1886_GAMMA);
+    return vec2(pow(1.0 - black, BT1886_GAMMA), black / (1.0 - black));
+}
+
+const float PQ_M1 = 2610.0 / 16384.0;
+const float PQ_M2 = 2523.0 / 4096.0 * 128.0;
+const float PQ_C1 = 3424.0 / 4096.0;
+const float PQ_C2 = 2413.0 / 4096.0 * 32.0;
+const float PQ_C3 = 2392.0 / 4096.0 * 32.0;
+
+vec3 pq_encode(vec3 c) {
+    vec3 y = pow(max(c, 0.0), vec3(PQ_M1));
+    return pow((PQ_C1 + PQ_C2 * y) / (1.0 + PQ_C3 * y), vec3(PQ_M2));
+}
+
+vec3 pq_decode(vec3 c) {
+    vec3 p = pow(max(c, 0.0), vec3(1.0 / PQ_M2));
+    return pow(max(p - PQ_C1, 0.0) / (PQ_C2 - PQ_C3 * p), vec3(1.0 / PQ_M1));
+}
+
+const float HLG_A = 0.17883277;
+const float HLG_B = 1.0 - 4.0 * HLG_A;
+const float HLG_C = 0.5599107;
+
+float hlg_encode(float c) {
+    c = max(c, 0.0);
+    return c <= 1.0 / 12.0 ? sqrt(3.0 * c) : HLG_A * log(12.0 * c - HLG_B) + HLG_C;
+}
+
+float hlg_decode(float c) {
+    c = max(c, 0.0);
+    return c <= 0.5 ? c * c / 3.0 : (exp((c - HLG_C) / HLG_A) + HLG_B) / 12.0;
+}
+
+// transfer is TransferFunction::id and param TransferFunction::param
+vec3 encode(vec3 c, int transfer, float param) {
+    switch (transfer) {
+    case 1:
+        return vec3(srgb_encode(c.r), srgb_encode(c.g), srgb_encode(c.b));
+    case 2:
+        return sign(c) * pow(abs(c), vec3(1.0 / param));
+    case 3: {
+        vec2 ab = bt1886_constants(param);
+        return pow(max(c, 0.0) / ab.x, vec3(1.0 / BT1886_GAMMA)) - ab.y;
+    }
+    case 4:
+        return pq_encode(c);
+    case 5:
+        return vec3(hlg_encode(c.r), hlg_encode(c.g), hlg_encode(c.b));
+    default:
+        return c;
+    }
+}
+
+vec3 decode(vec3 c, int transfer, float param) {
+    switch (transfer) {
+    case 1:
+        return vec3(srgb_decode(c.r), srgb_decode(c.g), srgb_decode(c.b));
+    case 2:
+        return sign(c) * pow(abs(c), vec3(param));
+    case 3: {
+        vec2 ab = bt1886_constants(param);
+        return ab.x * pow(max(c + ab.y, 0.0), vec3(BT1886_GAMMA));
+    }
+    case 4:
+        return pq_decode(c);
+    case 5:
+        return vec3(hlg_decode(c.r), hlg_decode(c.g), hlg_decode(c.b));
+    default:
+        return c;
+    }
+}
+
+// Brettel 1997 half plane matrices and separation plane normals of
+// protan, deutan and tritan as in color/cvd.rs
+const mat3 BRETTEL_H1[3] = mat3[3](
+    transpose(mat3(0.14980, 1.19548, -0.34528, 0.10764, 0.84864, 0.04372, 0.00384, -0.00540, 1.00156)),
+    transpose(mat3(0.36477, 0.86381, -0.22858, 0.26294, 0.64245, 0.09462, -0.02006, 0.02728, 0.99278)),
+    transpose(mat3(1.01277, 0.13548, -0.14826, -0.01243, 0.86812, 0.14431, 0.07589, 0.80500, 0.11911))
+);
+const mat3 BRETTEL_H2[3] = mat3[3](
+    transpose(mat3(0.14570, 1.16172, -0.30742, 0.10816, 0.85291, 0.03892, 0.00386, -0.00524, 1.00139)),
+    transpose(mat3(0.37298, 0.88166, -0.25464, 0.25954, 0.63506, 0.10540, -0.01980, 0.02784, 0.99196)),
+    transpose(mat3(0.93678, 0.18979, -0.12657, 0.06154, 0.81526, 0.12320, -0.37562, 1.12767, 0.24796))
+);
+const vec3 BRETTEL_NORMAL[3] = vec3[3](
+    vec3(0.00048, 0.00393, -0.00441),
+    vec3(-0.00281, -0.00611, 0.00892),
+    vec3(0.03901, -0.02788, -0.01113)
+);
+
+vec3 brettel(vec3 rgb, int deficiency, float severity) {
+    mat3 h = dot(rgb, BRETTEL_NORMAL[deficiency]) >= 0.0
+        ? BRETTEL_H1[deficiency]
+        : BRETTEL_H2[deficiency];
+    return mix(rgb, h * rgb, severity);
+}
+
+// Gamut mapping as in color/gamut.rs
+
+const float GAMUT_EPSILON = 1e-4;
+const int CHROMA_STEPS = 16;
+
+const mat3 RGB_TO_XYZ = transpose(mat3(
+    0.412453, 0.35758, 0.180423,
+    0.212671, 0.71516, 0.072169,
+    0.019334, 0.119193, 0.950227
+));
+const mat3 XYZ_TO_RGB = transpose(mat3(
+    3.240479, -1.53715, -0.498535,
+    -0.969256, 1.875991, 0.041556,
+    0.055648, -0.204043, 1.057311
+));
+const vec3 D65 = vec3(0.950456, 1.0, 1.088754);
+// Luminance Y of linear sRGB
+const vec3 LUMINANCE = transpose(RGB_TO_XYZ)[1];
+
+bool in_gamut(vec3 rgb) {
+    return all(greaterThanEqual(rgb, vec3(-GAMUT_EPSILON)))
+        && all(lessThanEqual(rgb, vec3(1.0 + GAMUT_EPSILON)));
+}
+
+vec3 soft_clip(vec3 rgb, float knee) {
+    float y = clamp(dot(LUMINANCE, rgb), 0.0, 1.0);
+    vec3 d = rgb - y;
+    float t_max = 1e30;
+    for (int i = 0; i < 3; i++) {
+        if (d[i] > 0.0) {
+            t_max = min(t_max, (1.0 - y) / d[i]);
+        } else if (d[i] < 0.0) {
+            t_max = min(t_max, y / -d[i]);
+        }
+    }
+    // Grays and colors whose luminance is out of range
+    if (t_max <= 0.0 || t_max >= 1e30) {
+        return vec3(y);
+    }
+    float s = 1.0 / t_max;
+    float compressed = s <= knee ? s : knee + (1.0 - knee) * (s - knee) / (1.0 - knee + s - knee);
+    return y + d * (compressed / s);
+}
+
+vec3 gamut_to_lch(vec3 rgb, bool oklch) {
+    return oklch ? lab_to_lch(rgb_to_oklab(rgb)) : lab_to_lch(xyz_to_lab(RGB_TO_XYZ * rgb, D65));
+}
+
+vec3 gamut_to_rgb(vec3 lch, bool oklch) {
+    return oklch ? oklab_to_rgb(lch_to_lab(lch)) : XYZ_TO_RGB * lab_to_xyz(lch_to_lab(lch), D65);
+}
+
+vec3 reduce_chroma(vec3 rgb, bool oklch) {
+    if (in_gamut(rgb)) {
+        return rgb;
+    }
+    vec3 lch = gamut_to_lch(rgb, oklch);
+    float l = clamp(lch.x, 0.0, gamut_to_lch(vec3(1.0), oklch).x);
+    float low = 0.0;
+    float high = lch.y;
+    for (int i = 0; i < CHROMA_STEPS; i++) {
+        float mid = 0.5 * (low + high);
+        if (in_gamut(gamut_to_rgb(vec3(l, mid, lch.z), oklch))) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    return clamp(gamut_to_rgb(vec3(l, low, lch.z), oklch), 0.0, 1.0);
+}
+
+// mapping is the id of GamutMapping::id_param
+vec3 gamut_map(vec3 rgb, int mapping, float param) {
+    switch (mapping) {
+    case 0:
+        return clamp(rgb, 0.0, 1.0);
+    case 1:
+        return soft_clip(rgb, 1.0);
+    case 2:
+        return reduce_chroma(rgb, false);
+    case 3:
+        return reduce_chroma(rgb, true);
+    default:
+        return soft_clip(rgb, param);
+    }
+}
+
+// Without a white point no value reaches 1
+vec3 reinhard(vec3 rgb, float white, bool has_white) {
+    float l = dot(rgb, LUMINANCE);
+    if (l <= 0.0) {
+        return vec3(0.0);
+    }
+    float mapped = has_white ? l * (1.0 + l / (white * white)) / (1.0 + l) : l / (1.0 + l);
+    return rgb * (mapped / l);
+}
+
+vec3 aces(vec3 x) {
+    x = max(x, 0.0);
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 hable_curve(vec3 x) {
+    const float A = 0.15, B = 0.50, C = 0.10, D = 0.20, E = 0.02, F = 0.30;
+    return (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F;
+}
+
+vec3 hable(vec3 x, float white) {
+    return hable_curve(2.0 * max(x, 0.0)) / hable_curve(vec3(2.0 * white));
+}
+
+const float AGX_MIN_EV = -12.47393;
+const float AGX_MAX_EV = 4.026069;
+
+const mat3 AGX_INSET = transpose(mat3(
+    0.842479062253094, 0.0784335999999992, 0.0792237451477643,
+    0.0423282422610123, 0.878468636469772, 0.0791661274605434,
+    0.0423756549057051, 0.0784336, 0.879142973793104
+));
+
+const mat3 AGX_OUTSET = transpose(mat3(
+    1.19687900512017, -0.0980208811401368, -0.0990297440797205,
+    -0.0528968517574562, 1.15190312990417, -0.0989611768448433,
+    -0.0529716355144438, -0.0980434501171241, 1.15107367264116
+));
+
+vec3 agx_contrast(vec3 x) {
+    vec3 x2 = x * x;
+    vec3 x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+vec3 agx(vec3 rgb) {
+    vec3 ev = clamp(log2(max(AGX_INSET * rgb, 1e-10)), AGX_MIN_EV, AGX_MAX_EV);
+    vec3 curve = agx_contrast((ev - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV));
+    return pow(max(AGX_OUTSET * curve, 0.0), vec3(2.2));
+}
+
+// mapping is ToneMapping::id
+vec3 tone_map(vec3 rgb, int mapping, float white) {
+    switch (mapping) {
+    case 0:
+        return reinhard(rgb, white, true);
+    case 1:
+        return aces(rgb);
+    case 2:
+        return hable(rgb, white);
+    case 3:
+        return agx(rgb);
+    default:
+        return reinhard(rgb, white, false);
+    }
+}
+
+void main() {
+    vec4 c = texture(image, v_tex_coords);
+    vec3 rgb = c.rgb;
+    switch (conversion) {
+    case 0:
+        rgb = xyz_to_lab(rgb, params.xyz);
+        break;
+    case 1:
+        rgb = lab_to_xyz(rgb, params.xyz);
+        break;
+    case 2:
+        rgb = lab_to_lch(rgb);
+        break;
+    case 3:
+        rgb = lch_to_lab(rgb);
+        break;
+    case 4:
+        rgb = rgb_to_oklab(rgb);
+        break;
+    case 5:
+        rgb = oklab_to_rgb(rgb);
+        break;
+    case 6:
+        rgb = rgb_to_hsv(rgb);
+        break;
+    case 7:
+        rgb = hsv_to_rgb(rgb);
+        break;
+    case 8:
+        rgb = rgb_to_hsl(rgb);
+        break;
+    case 9:
+        rgb = hsl_to_rgb(rgb);
+        break;
+    case 10:
+        rgb = rgb_to_hsi(rgb);
+        break;
+    case 11:
+        rgb = hsi_to_rgb(rgb);
+        break;
+    case 12:
+        rgb = encode(rgb, int(params.x), params.y);
+        break;
+    case 13:
+        rgb = decode(rgb, int(params.x), params.y);
+        break;
+    case 14:
+        rgb = brettel(rgb, int(params.x), params.y);
+        break;
+    case 15:
+        rgb = gamut_map(rgb, int(params.x), params.y);
+        break;
+    case 16:
+        rgb = in_gamut(rgb) ? rgb : params.rgb;
+        break;
+    case 17:
+        rgb = tone_map(rgb, int(params.x), params.y);
+        break;
+    }
+    color = vec4(rgb, c.a);
+}